@@ -0,0 +1,16 @@
+#!/usr/bin/env bash
+
+# Builds the Python extension module into python/ai2048.so, then runs its tests against the
+# local interpreter (whichever python3 is on the PATH).
+
+set -e
+
+cd "$(git rev-parse --show-toplevel)" || exit 1
+
+# Tells pyo3 not to link against libpython, since the interpreter loading us already has it
+export PYO3_BUILD_EXTENSION_MODULE=1
+cargo rustc --lib --release --no-default-features --features python --crate-type cdylib
+
+cp target/release/libai2048.so python/ai2048.so
+cd python
+python3 -m unittest -v test_ai2048
@@ -0,0 +1,98 @@
+"""Tests for the ai2048 extension module; see test.sh for running them."""
+
+import unittest
+
+import ai2048
+
+
+class GameStateTests(unittest.TestCase):
+    def test_new_game(self):
+        game = ai2048.GameState(size=4, seed=7)
+        self.assertEqual(game.size, 4)
+        self.assertEqual(len(game.board), 4)
+        self.assertEqual(sum(tile != 0 for row in game.board for tile in row), 1)
+        self.assertEqual(game.score, 0)
+        self.assertFalse(game.game_over)
+
+    def test_seeded_games_match(self):
+        a = ai2048.GameState(seed=3, spawn_policy="classic")
+        b = ai2048.GameState(seed=3, spawn_policy="classic")
+        for _ in range(20):
+            m = a.legal_moves()[0]
+            self.assertTrue(a.move(m))
+            self.assertTrue(b.move(m))
+        self.assertEqual(a.board, b.board)
+        self.assertEqual(repr(a), repr(b))
+
+    def test_moves_by_name_or_index(self):
+        game = ai2048.GameState(position="2,2,.,./.,.,.,./.,.,.,./.,.,.,.:0")
+        self.assertEqual(game.legal_moves(), ["down", "left", "right"])
+        self.assertEqual(game.action_mask(), [False, True, True, True])
+        self.assertFalse(game.move("up"))
+        self.assertTrue(game.move(2))
+        self.assertEqual(game.board[0][0], 4)
+        self.assertEqual(game.score, 4)
+        self.assertTrue(game.undo())
+        self.assertEqual(game.board[0][:2], [2, 2])
+
+        with self.assertRaises(ValueError):
+            game.move("sideways")
+        with self.assertRaises(ValueError):
+            game.move(4)
+
+    def test_bad_arguments(self):
+        with self.assertRaises(ValueError):
+            ai2048.GameState(size=12)
+        with self.assertRaises(ValueError):
+            ai2048.GameState(spawn_policy="sometimes")
+        with self.assertRaises(ValueError):
+            ai2048.GameState(position="2,4/8")
+
+
+class VecEnvTests(unittest.TestCase):
+    def test_reset_and_step(self):
+        env = ai2048.VecEnv(3, size=4, encoding="exponent")
+        self.assertEqual((env.num_envs, env.num_features, env.num_actions), (3, 16, 4))
+
+        obs, info = env.reset(seed=5)
+        self.assertEqual(len(obs), 3)
+        self.assertTrue(all(len(row) == 16 for row in obs))
+        self.assertEqual(len(info["action_mask"]), 3)
+
+        actions = [mask.index(True) for mask in info["action_mask"]]
+        obs, rewards, terminated, truncated, info = env.step(actions)
+        self.assertEqual((len(obs), len(rewards), len(terminated), len(truncated)), (3, 3, 3, 3))
+        self.assertEqual(info["num_steps"], [1, 1, 1])
+        self.assertEqual(info["illegal_move"], [False, False, False])
+        self.assertEqual(rewards, [float(delta) for delta in info["score_delta"]])
+
+        again = ai2048.VecEnv(3, size=4, encoding="exponent")
+        again.reset(seed=5)
+        self.assertEqual(again.step(actions)[0], obs)
+
+    def test_episodes_end_and_reset(self):
+        env = ai2048.VecEnv(2, size=3, reward={"survival": 1.0}, max_steps=50)
+        obs, info = env.reset(seed=1)
+        self.assertEqual(len(obs[0]), 3 * 3 * 18)
+
+        ended = 0
+        for _ in range(200):
+            actions = [mask.index(True) for mask in info["action_mask"]]
+            obs, rewards, terminated, truncated, info = env.step(actions)
+            self.assertEqual(rewards, [1.0, 1.0])
+            ended += sum(terminated) + sum(truncated)
+        self.assertGreater(ended, 0)
+
+    def test_bad_arguments(self):
+        with self.assertRaises(ValueError):
+            ai2048.VecEnv(2, encoding="pixels")
+        with self.assertRaises(ValueError):
+            ai2048.VecEnv(2, reward={"style": 1.0})
+        env = ai2048.VecEnv(2)
+        env.reset()
+        with self.assertRaises(ValueError):
+            env.step([0])
+
+
+if __name__ == "__main__":
+    unittest.main()
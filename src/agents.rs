@@ -0,0 +1,172 @@
+//! Anything that can play the game by itself. The TUI and evaluation code only care about this
+//! trait, so neural nets and search-based players can be swapped freely.
+
+use std::io;
+use std::str::FromStr;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+
+mod expectimax;
+mod external;
+mod mcts;
+#[cfg(feature = "burn")]
+mod model;
+mod monte_carlo;
+
+#[cfg(test)]
+mod tests;
+
+pub use expectimax::ExpectimaxAgent;
+pub use external::ExternalAgent;
+pub use mcts::CRITIC_SCALE;
+pub use mcts::ChanceNodes;
+pub use mcts::LeafValue;
+pub use mcts::MctsAgent;
+pub use mcts::MctsConfig;
+pub use mcts::SearchResult;
+#[cfg(feature = "burn")]
+pub use model::ModelAgent;
+pub use monte_carlo::MonteCarloAgent;
+pub use monte_carlo::MonteCarloConfig;
+pub use monte_carlo::RolloutObjective;
+pub use monte_carlo::RolloutPolicy;
+pub use monte_carlo::mean_rollout_score;
+pub use monte_carlo::rollout;
+pub use monte_carlo::rollout_from_afterstate;
+pub use monte_carlo::rollouts_from_afterstate;
+
+/// A player that picks its own moves
+pub trait Agent<const N: usize> {
+    /// Short human-readable description, used for banners and logs
+    fn name(&self) -> String;
+
+    /// Pick the next move. Only called on states which are not finished. Errors if the agent
+    /// can't answer at all (e.g. an external program crashed).
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move>;
+
+    /// Whatever the agent can tell us about how it feels about each move. Agents fill in the
+    /// parts that make sense for them; the default is to say nothing.
+    fn evaluate(&mut self, _state: &GameState<N>) -> MoveEvaluation {
+        MoveEvaluation::default()
+    }
+
+    /// Pick the next move and evaluate the position together. Search agents override this so
+    /// that both come from a single search, which costs half as much and means the evaluation
+    /// describes the search that actually picked the move.
+    fn choose_with_evaluation(&mut self, state: &GameState<N>) -> io::Result<(Move, MoveEvaluation)> {
+        let evaluation = self.evaluate(state);
+        Ok((self.choose_move(state)?, evaluation))
+    }
+}
+
+/// An agent's opinion of a position. Per-move arrays are indexed by `Move::to_idx`.
+#[derive(Clone, Debug, Default)]
+pub struct MoveEvaluation {
+    /// Probability of picking each move (e.g. softmax of a policy head)
+    pub policy: Option<[f32; 4]>,
+    /// Estimated value of the position itself (e.g. a critic head)
+    pub value: Option<f32>,
+    /// Expected score after each move, or None if the move is illegal
+    pub expected_scores: Option<[Option<f32>; 4]>,
+}
+
+/// Command-line description of an agent, e.g. `model:checkpoint.bin`, `expectimax:3` or
+/// `mc:rollouts=200,policy=greedy`
+#[derive(Clone, Debug)]
+pub enum AgentSpec {
+    /// A freshly initialized (random) PolicyNet
+    Untrained,
+    /// A PolicyNet loaded from the given checkpoint
+    Model(String),
+    /// Expectimax search with the given depth
+    Expectimax(usize),
+    /// Monte Carlo rollouts from every move
+    MonteCarlo(MonteCarloConfig),
+    /// Monte Carlo tree search, with a model checkpoint if it uses priors or critic leaves
+    Mcts(MctsConfig, Option<String>),
+    /// A separate program speaking the JSON-lines protocol, run through the shell
+    External(String),
+}
+
+impl FromStr for AgentSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+
+        match (kind, arg) {
+            ("untrained", None) => Ok(AgentSpec::Untrained),
+            ("model", Some(path)) if !path.is_empty() => Ok(AgentSpec::Model(path.to_string())),
+            ("external", Some(command)) if !command.is_empty() => Ok(AgentSpec::External(command.to_string())),
+            ("expectimax", None) => Ok(AgentSpec::Expectimax(2)),
+            ("expectimax", Some(depth)) => depth
+                .parse()
+                .map(AgentSpec::Expectimax)
+                .map_err(|_| format!("Invalid expectimax depth '{depth}'")),
+            ("mc", None) => Ok(AgentSpec::MonteCarlo(MonteCarloConfig::default())),
+            ("mc", Some(options)) => options.parse().map(AgentSpec::MonteCarlo),
+            ("mcts", options) => parse_mcts(options.unwrap_or_default()),
+            _ => Err(format!(
+                "Unknown agent '{s}'; expected one of: untrained, model:<path>, expectimax[:<depth>], mc[:<options>], mcts[:<options>], external:<command>"
+            )),
+        }
+    }
+}
+
+/// MCTS options, plus `model=<path>` for the checkpoint
+fn parse_mcts(options: &str) -> Result<AgentSpec, String> {
+    let mut model = None;
+    let rest: Vec<&str> = options
+        .split(',')
+        .filter(|option| match option.strip_prefix("model=") {
+            Some(path) => {
+                model = Some(path.to_string());
+                false
+            }
+            None => true,
+        })
+        .collect();
+
+    let config = MctsConfig::parse_options(&rest.join(","), "sims, ms, c, leaf, priors, widen, widen_k or model")?;
+    if config.needs_model() && model.is_none() {
+        return Err("MCTS with priors=true or leaf=critic needs model=<path>".to_string());
+    }
+    Ok(AgentSpec::Mcts(config, model))
+}
+
+impl AgentSpec {
+    /// Build the agent for games whose pieces spawn with `spawn_policy`, which the search agents
+    /// need to know to weigh what might spawn next
+    pub fn build<const N: usize>(&self, spawn_policy: SpawnPolicy) -> io::Result<Box<dyn Agent<N>>> {
+        let agent: Box<dyn Agent<N>> = match self {
+            #[cfg(feature = "burn")]
+            AgentSpec::Untrained => Box::new(ModelAgent::untrained()),
+            #[cfg(feature = "burn")]
+            AgentSpec::Model(path) => Box::new(ModelAgent::load(path)?),
+            #[cfg(not(feature = "burn"))]
+            AgentSpec::Untrained | AgentSpec::Model(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "Model agents need ai2048 to be built with the burn feature",
+                ));
+            }
+            AgentSpec::Expectimax(depth) => Box::new(ExpectimaxAgent::new(*depth).with_spawn_policy(spawn_policy)),
+            AgentSpec::MonteCarlo(config) => Box::new(MonteCarloAgent::new(*config).with_spawn_policy(spawn_policy)),
+            AgentSpec::Mcts(config, model) => {
+                let model = model
+                    .as_ref()
+                    .map(|path| AgentSpec::Model(path.clone()).build::<N>(spawn_policy))
+                    .transpose()?;
+                Box::new(MctsAgent::new(MctsConfig { spawn_policy, ..*config }, model))
+            }
+            AgentSpec::External(command) => Box::new(ExternalAgent::spawn(command)?),
+        };
+
+        Ok(agent)
+    }
+}
@@ -0,0 +1,119 @@
+//! Depth-limited expectimax search. Decision nodes take the best move, chance nodes average over
+//! every possible spawned piece, and leaves are valued at their current score.
+
+use std::io;
+
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+
+/// Looks a fixed number of moves ahead, averaging over every possible spawn, and takes the
+/// move with the best expected score
+pub struct ExpectimaxAgent {
+    /// How many moves to look ahead
+    depth: usize,
+    /// What the chance nodes expect to spawn
+    spawn_policy: SpawnPolicy,
+}
+
+impl ExpectimaxAgent {
+    /// Look `depth` moves ahead (at least one)
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth: depth.max(1),
+            spawn_policy: SpawnPolicy::default(),
+        }
+    }
+
+    /// Search games whose pieces spawn this way
+    pub fn with_spawn_policy(self, spawn_policy: SpawnPolicy) -> Self {
+        Self { spawn_policy, ..self }
+    }
+
+    /// Expected score after `depth` moves for each legal move, indexed by `Move::to_idx`
+    pub fn move_values<const N: usize>(&self, state: &GameState<N>) -> [Option<f32>; 4] {
+        let p_two = self.spawn_policy.two_probability() as f32;
+        let mut out = [None; 4];
+        for m in Move::ALL {
+            if state.is_legal_move(m) {
+                out[m.to_idx()] = Some(chance_value(&state.slide(m), self.depth - 1, p_two));
+            }
+        }
+        out
+    }
+}
+
+/// The legal move with the highest value
+fn best_move(values: &[Option<f32>; 4]) -> Move {
+    let mut best: Option<(Move, f32)> = None;
+    for m in Move::ALL {
+        if let Some(v) = values[m.to_idx()]
+            && best.is_none_or(|(_, best_v)| v > best_v)
+        {
+            best = Some((m, v));
+        }
+    }
+
+    best.expect("Should only be asked to move in unfinished games").0
+}
+
+/// Value of a state where it's our turn to move; `p_two` is the chance that a spawned piece is a 2
+fn decision_value<const N: usize>(state: &GameState<N>, depth: usize, p_two: f32) -> f32 {
+    if depth == 0 {
+        return state.current_score() as f32;
+    }
+
+    Move::ALL
+        .into_iter()
+        .filter(|&m| state.is_legal_move(m))
+        .map(|m| chance_value(&state.slide(m), depth - 1, p_two))
+        .reduce(f32::max)
+        .unwrap_or(state.current_score() as f32)
+}
+
+/// Value of an afterstate, averaged over every piece that might be placed next
+fn chance_value<const N: usize>(afterstate: &GameState<N>, depth: usize, p_two: f32) -> f32 {
+    let empty = afterstate.empty_cells();
+    if empty.is_empty() {
+        return decision_value(afterstate, depth, p_two);
+    }
+
+    let total: f32 = empty
+        .iter()
+        .map(|&(x, y)| {
+            p_two * decision_value(&afterstate.with_tile(x, y, 1), depth, p_two)
+                + (1.0 - p_two) * decision_value(&afterstate.with_tile(x, y, 2), depth, p_two)
+        })
+        .sum();
+
+    total / empty.len() as f32
+}
+
+impl<const N: usize> Agent<N> for ExpectimaxAgent {
+    fn name(&self) -> String {
+        format!("expectimax (depth {})", self.depth)
+    }
+
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        Ok(best_move(&self.move_values(state)))
+    }
+
+    fn evaluate(&mut self, state: &GameState<N>) -> MoveEvaluation {
+        MoveEvaluation {
+            expected_scores: Some(self.move_values(state)),
+            ..Default::default()
+        }
+    }
+
+    fn choose_with_evaluation(&mut self, state: &GameState<N>) -> io::Result<(Move, MoveEvaluation)> {
+        let values = self.move_values(state);
+        let evaluation = MoveEvaluation {
+            expected_scores: Some(values),
+            ..Default::default()
+        };
+        Ok((best_move(&values), evaluation))
+    }
+}
@@ -0,0 +1,95 @@
+//! An agent that lives in another process and speaks the JSON-lines protocol (see `protocol`)
+//! over its stdin and stdout
+
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+
+use super::Agent;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::protocol::Request;
+use crate::protocol::Response;
+use crate::protocol::StateView;
+use crate::protocol::answer_query;
+use crate::protocol::parse_request;
+use crate::protocol::write_message;
+
+/// A separate program, sent each position and asked for a move
+pub struct ExternalAgent {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl ExternalAgent {
+    /// Start the agent by running `command` through the shell. Its stderr is discarded, since
+    /// it would otherwise be written over the TUI; a bot that wants a log should write a file.
+    pub fn spawn(command: &str) -> io::Result<Self> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let stdin = child.stdin.take().expect("stdin was piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout was piped"));
+
+        Ok(Self {
+            command: command.to_string(),
+            child,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// Send the position, then answer the agent's queries until it makes a move
+    fn ask<const N: usize>(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        write_message(&mut self.stdin, &Response::with_state(StateView::of(state)))?;
+
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "the agent closed its output"));
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match parse_request(&line) {
+                Ok(Request::Move { m }) => return Ok(m),
+                Ok(request) => answer_query(state, &request)
+                    .unwrap_or_else(|| Response::error("Only state, legal_moves and move are allowed while it's your turn")),
+                Err(e) => Response::error(e),
+            };
+            write_message(&mut self.stdin, &response)?;
+        }
+    }
+}
+
+impl<const N: usize> Agent<N> for ExternalAgent {
+    fn name(&self) -> String {
+        format!("external: {}", self.command)
+    }
+
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        self.ask(state)
+            .map_err(|e| io::Error::new(e.kind(), format!("External agent '{}' failed: {e}", self.command)))
+    }
+}
+
+impl Drop for ExternalAgent {
+    fn drop(&mut self) {
+        // it may well have exited already, in which case there's nothing to do
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
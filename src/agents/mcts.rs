@@ -0,0 +1,524 @@
+//! Monte Carlo tree search over 2048's two kinds of turn: decision nodes, where we pick one of the
+//! four moves, and chance nodes, where a piece spawns on the board the move left behind.
+//!
+//! Every node is valued at the final score its simulations expect. Leaves are judged by playing
+//! them out (see `monte_carlo`) or by a model's critic, which estimates the points still to come.
+//! Moves are picked by UCT, or by PUCT when a model's policy supplies priors. Chance nodes either
+//! sample a spawn on every visit, or use progressive widening to only try new spawns as the node
+//! gets more visits.
+
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
+use crate::agents::RolloutPolicy;
+use crate::agents::rollout;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+
+/// Points per unit of a critic's value. Critics learn the points still to come scaled down by
+/// this, so their targets stay small even when thousands of points are left.
+pub const CRITIC_SCALE: f32 = 1000.0;
+
+/// How a new leaf is valued
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeafValue {
+    /// Final score of one game played out with the given policy
+    Rollout(RolloutPolicy),
+    /// The current score plus the model's critic value, read as the points still to come in units
+    /// of `CRITIC_SCALE` (which is what AlphaZero training teaches the critic)
+    Critic,
+}
+
+/// Which spawns a chance node looks at
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChanceNodes {
+    /// A fresh spawn, drawn like the game would, on every visit
+    Sampled,
+    /// A new spawn only while the node has fewer than `k * visits^alpha` of them; otherwise one
+    /// of the spawns already tried, in proportion to how likely it is
+    Widened {
+        /// Spawns allowed on the first visit
+        k: f32,
+        /// How fast the allowance grows with visits
+        alpha: f32,
+    },
+}
+
+/// Settings for `MctsAgent`. On the command line these are written as comma-separated
+/// `key=value` pairs, e.g. `sims=400,ms=100,c=1.4,leaf=greedy,widen=0.5,widen_k=2`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MctsConfig {
+    /// Simulations per move
+    pub simulations: usize,
+    /// Stop early once this much time has gone into a move (after at least one simulation)
+    pub time_budget: Option<Duration>,
+    /// Weight of the exploration term in UCT or PUCT
+    pub exploration: f32,
+    /// How leaves are valued
+    pub leaf: LeafValue,
+    /// Use the model's policy as priors (PUCT) instead of plain UCT
+    pub priors: bool,
+    /// Which spawns chance nodes look at
+    pub chance: ChanceNodes,
+    /// How pieces spawn in the game being searched; not part of the written form, since it
+    /// comes from the game
+    pub spawn_policy: SpawnPolicy,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        Self {
+            simulations: 200,
+            time_budget: None,
+            exploration: std::f32::consts::SQRT_2,
+            leaf: LeafValue::Rollout(RolloutPolicy::Random),
+            priors: false,
+            chance: ChanceNodes::Sampled,
+            spawn_policy: SpawnPolicy::default(),
+        }
+    }
+}
+
+impl MctsConfig {
+    /// Whether searching needs a model to ask
+    pub fn needs_model(&self) -> bool {
+        self.priors || self.leaf == LeafValue::Critic
+    }
+
+    /// Parse the written form; `expected` lists the keys for the error on an unknown one, since
+    /// agent specs take `model` as well
+    pub(crate) fn parse_options(s: &str, expected: &str) -> Result<Self, String> {
+        let mut config = MctsConfig::default();
+        let mut widen_alpha = None;
+        let mut widen_k = None;
+        for option in s.split(',').filter(|option| !option.is_empty()) {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(format!("Expected key=value, got '{option}'"));
+            };
+            let invalid = || format!("Invalid value '{value}' for {key}");
+            let positive = || match value.parse::<f32>() {
+                Ok(x) if x.is_finite() && x > 0.0 => Ok(x),
+                _ => Err(invalid()),
+            };
+            match key {
+                "sims" => {
+                    config.simulations = match value.parse() {
+                        Ok(simulations) if simulations > 0 => simulations,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "ms" => config.time_budget = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+                "c" => config.exploration = positive()?,
+                "leaf" => {
+                    config.leaf = match value {
+                        "critic" => LeafValue::Critic,
+                        policy => LeafValue::Rollout(policy.parse().map_err(|_| invalid())?),
+                    }
+                }
+                "priors" => config.priors = value.parse().map_err(|_| invalid())?,
+                "widen" => widen_alpha = Some(positive()?),
+                "widen_k" => widen_k = Some(positive()?),
+                _ => {
+                    return Err(format!("Unknown MCTS option '{key}'; expected {expected}"));
+                }
+            }
+        }
+
+        config.chance = match (widen_alpha, widen_k) {
+            (Some(alpha), k) => ChanceNodes::Widened {
+                k: k.unwrap_or(1.0),
+                alpha,
+            },
+            (None, Some(_)) => return Err("widen_k needs widen".to_string()),
+            (None, None) => ChanceNodes::Sampled,
+        };
+        Ok(config)
+    }
+}
+
+impl FromStr for MctsConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_options(s, "sims, ms, c, leaf, priors, widen or widen_k")
+    }
+}
+
+/// What a search found at the root, per move (indexed by `Move::to_idx`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// Simulations that went through each move
+    pub visits: [u32; 4],
+    /// Mean final score of those simulations, or None if the move was never tried
+    pub values: [Option<f32>; 4],
+}
+
+impl SearchResult {
+    /// Fraction of simulations that went through each move
+    pub fn visit_distribution(&self) -> [f32; 4] {
+        let total: u32 = self.visits.iter().sum();
+        self.visits.map(|v| v as f32 / total.max(1) as f32)
+    }
+
+    /// The most visited move, with the better value breaking ties
+    pub fn best_move(&self) -> Move {
+        Move::ALL
+            .into_iter()
+            .filter(|m| self.visits[m.to_idx()] > 0)
+            .max_by(|a, b| {
+                let key = |m: &Move| (self.visits[m.to_idx()], self.values[m.to_idx()].unwrap_or(f32::MIN));
+                key(a).partial_cmp(&key(b)).expect("Values aren't NaN")
+            })
+            .expect("A search always tries at least one move")
+    }
+
+    /// The visit distribution as a policy, and each move's mean value as its expected score
+    pub fn evaluation(&self) -> MoveEvaluation {
+        MoveEvaluation {
+            policy: Some(self.visit_distribution()),
+            value: None,
+            expected_scores: Some(self.values),
+        }
+    }
+}
+
+/// A position where it's our turn
+struct DecisionNode<const N: usize> {
+    state: GameState<N>,
+    visits: u32,
+    value_sum: f64,
+    /// Set once the node has been valued as a leaf; only then is it searched below
+    evaluated: bool,
+    priors: Option<[f32; 4]>,
+    /// Chance node for each move tried so far
+    children: [Option<usize>; 4],
+}
+
+/// A board just after a move, waiting for its spawn
+struct ChanceNode<const N: usize> {
+    afterstate: GameState<N>,
+    visits: u32,
+    value_sum: f64,
+    outcomes: Vec<Outcome>,
+}
+
+/// One spawn tried from a chance node
+struct Outcome {
+    x: usize,
+    y: usize,
+    exponent: u8,
+    probability: f32,
+    /// The decision node it leads to
+    child: usize,
+}
+
+/// Nodes live in two arenas and point at each other by index
+struct Tree<const N: usize> {
+    decisions: Vec<DecisionNode<N>>,
+    chances: Vec<ChanceNode<N>>,
+    /// Range of every value backed up so far, to scale them into [0, 1] for selection
+    min_value: f64,
+    max_value: f64,
+}
+
+impl<const N: usize> Tree<N> {
+    fn new(root: GameState<N>) -> Self {
+        let mut tree = Self {
+            decisions: Vec::new(),
+            chances: Vec::new(),
+            min_value: f64::INFINITY,
+            max_value: f64::NEG_INFINITY,
+        };
+        tree.add_decision(root);
+        tree
+    }
+
+    fn add_decision(&mut self, state: GameState<N>) -> usize {
+        self.decisions.push(DecisionNode {
+            state,
+            visits: 0,
+            value_sum: 0.0,
+            evaluated: false,
+            priors: None,
+            children: [None; 4],
+        });
+        self.decisions.len() - 1
+    }
+
+    fn add_chance(&mut self, afterstate: GameState<N>) -> usize {
+        self.chances.push(ChanceNode {
+            afterstate,
+            visits: 0,
+            value_sum: 0.0,
+            outcomes: Vec::new(),
+        });
+        self.chances.len() - 1
+    }
+
+    /// Mean value of a chance node, scaled into [0, 1] by the values seen so far
+    fn normalized_value(&self, chance: usize) -> f64 {
+        let node = &self.chances[chance];
+        let mean = node.value_sum / node.visits.max(1) as f64;
+        if self.max_value > self.min_value {
+            (mean - self.min_value) / (self.max_value - self.min_value)
+        } else {
+            0.5
+        }
+    }
+}
+
+/// Plays the most visited move after a Monte Carlo tree search, optionally guided by a model
+pub struct MctsAgent<const N: usize> {
+    config: MctsConfig,
+    /// Supplies priors and critic values through `Agent::evaluate`
+    model: Option<Box<dyn Agent<N>>>,
+    rng: StdRng,
+}
+
+impl<const N: usize> MctsAgent<N> {
+    /// `model` is only asked for anything if the config uses priors or critic leaves, and must be
+    /// given if it does
+    pub fn new(config: MctsConfig, model: Option<Box<dyn Agent<N>>>) -> Self {
+        assert!(
+            model.is_some() || !config.needs_model(),
+            "MCTS with priors or critic leaves needs a model"
+        );
+        Self {
+            config,
+            model,
+            rng: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Make the search, and so the agent's choices, repeatable
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Search from `state`, which must not be finished
+    pub fn search(&mut self, state: &GameState<N>) -> SearchResult {
+        let start = Instant::now();
+        let mut tree = Tree::new(*state);
+
+        // value the root up front, so every simulation goes through a move
+        let value = self.evaluate_leaf(&mut tree, 0);
+        self.backup(&mut tree, &[0], &[], value);
+
+        for i in 0..self.config.simulations.max(1) {
+            if i > 0 && self.config.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+                break;
+            }
+            self.simulate(&mut tree);
+        }
+
+        let root = &tree.decisions[0];
+        SearchResult {
+            visits: root.children.map(|child| child.map_or(0, |c| tree.chances[c].visits)),
+            values: root.children.map(|child| {
+                child.map(|c| {
+                    let node = &tree.chances[c];
+                    (node.value_sum / node.visits.max(1) as f64) as f32
+                })
+            }),
+        }
+    }
+
+    /// Walk down from the root to a new leaf or the end of a game, then back its value up
+    fn simulate(&mut self, tree: &mut Tree<N>) {
+        let mut decision_path = vec![0];
+        let mut chance_path = Vec::new();
+        let mut d = 0;
+
+        let value = loop {
+            let node = &tree.decisions[d];
+            if node.state.is_finished() {
+                break node.state.current_score() as f64;
+            }
+            if !node.evaluated {
+                break self.evaluate_leaf(tree, d);
+            }
+
+            let m = self.select_move(tree, d);
+            let c = match tree.decisions[d].children[m.to_idx()] {
+                Some(c) => c,
+                None => {
+                    let c = tree.add_chance(tree.decisions[d].state.slide(m));
+                    tree.decisions[d].children[m.to_idx()] = Some(c);
+                    c
+                }
+            };
+            chance_path.push(c);
+
+            d = self.select_outcome(tree, c);
+            decision_path.push(d);
+        };
+
+        self.backup(tree, &decision_path, &chance_path, value);
+    }
+
+    fn backup(&self, tree: &mut Tree<N>, decision_path: &[usize], chance_path: &[usize], value: f64) {
+        tree.min_value = tree.min_value.min(value);
+        tree.max_value = tree.max_value.max(value);
+        for &d in decision_path {
+            tree.decisions[d].visits += 1;
+            tree.decisions[d].value_sum += value;
+        }
+        for &c in chance_path {
+            tree.chances[c].visits += 1;
+            tree.chances[c].value_sum += value;
+        }
+    }
+
+    /// Value a decision node seen for the first time, and remember its priors
+    fn evaluate_leaf(&mut self, tree: &mut Tree<N>, d: usize) -> f64 {
+        let node = &mut tree.decisions[d];
+        node.evaluated = true;
+        let state = node.state;
+        if state.is_finished() {
+            return state.current_score() as f64;
+        }
+
+        let evaluation = match &mut self.model {
+            Some(model) if self.config.needs_model() => model.evaluate(&state),
+            _ => MoveEvaluation::default(),
+        };
+        if self.config.priors {
+            node.priors = evaluation.policy;
+        }
+
+        match self.config.leaf {
+            LeafValue::Rollout(policy) => {
+                let mut spawns = RngPlacement::new_from_seed(self.rng.random()).with_spawn_policy(self.config.spawn_policy);
+                rollout(&state, policy, &mut spawns, &mut self.rng).current_score() as f64
+            }
+            LeafValue::Critic => state.current_score() as f64 + (evaluation.value.unwrap_or(0.0) * CRITIC_SCALE) as f64,
+        }
+    }
+
+    /// UCT, or PUCT if the node has priors. Under UCT, untried moves go first.
+    fn select_move(&self, tree: &Tree<N>, d: usize) -> Move {
+        let node = &tree.decisions[d];
+        let legal: Vec<Move> = Move::ALL.into_iter().filter(|&m| node.state.is_legal_move(m)).collect();
+        let parent_visits = node.visits.max(1) as f64;
+        let c = self.config.exploration as f64;
+
+        if node.priors.is_none()
+            && let Some(&untried) = legal.iter().find(|m| node.children[m.to_idx()].is_none())
+        {
+            return untried;
+        }
+
+        // priors only over the legal moves
+        let prior_total: f64 = node.priors.map_or(1.0, |p| legal.iter().map(|m| p[m.to_idx()] as f64).sum());
+
+        let score = |m: Move| {
+            let (visits, q) = match node.children[m.to_idx()] {
+                Some(child) => (tree.chances[child].visits as f64, tree.normalized_value(child)),
+                None => (0.0, 0.0),
+            };
+            match node.priors {
+                Some(priors) => {
+                    let prior = if prior_total > 0.0 {
+                        priors[m.to_idx()] as f64 / prior_total
+                    } else {
+                        1.0 / legal.len() as f64
+                    };
+                    q + c * prior * parent_visits.sqrt() / (1.0 + visits)
+                }
+                None => q + c * (parent_visits.ln() / visits).sqrt(),
+            }
+        };
+
+        legal
+            .iter()
+            .map(|&m| (m, score(m)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .expect("Only searching unfinished games")
+            .0
+    }
+
+    /// The decision node a visit to chance node `c` continues to, added if it's new
+    fn select_outcome(&mut self, tree: &mut Tree<N>, c: usize) -> usize {
+        let node = &tree.chances[c];
+        let widen = match self.config.chance {
+            ChanceNodes::Sampled => true,
+            ChanceNodes::Widened { k, alpha } => {
+                (node.outcomes.len() as f64) < (k as f64 * ((node.visits + 1) as f64).powf(alpha as f64)).ceil()
+            }
+        };
+
+        if !widen {
+            let total: f32 = node.outcomes.iter().map(|o| o.probability).sum();
+            let mut pick = self.rng.random_range(0.0..total);
+            for outcome in &node.outcomes {
+                if pick < outcome.probability {
+                    return outcome.child;
+                }
+                pick -= outcome.probability;
+            }
+            return node.outcomes.last().expect("Only picking from spawns already tried").child;
+        }
+
+        // a spawn drawn the way the game draws them
+        let afterstate = node.afterstate;
+        let empty = afterstate.empty_cells();
+        let (x, y) = empty[self.rng.random_range(0..empty.len())];
+        let p_two = self.config.spawn_policy.two_probability();
+        let is_two = self.rng.random_bool(p_two);
+        let exponent = if is_two { 1 } else { 2 };
+
+        if let Some(outcome) = node.outcomes.iter().find(|o| (o.x, o.y, o.exponent) == (x, y, exponent)) {
+            return outcome.child;
+        }
+
+        let p_value = if is_two { p_two } else { 1.0 - p_two };
+        let probability = (p_value / empty.len() as f64) as f32;
+        let child = tree.add_decision(afterstate.with_tile(x, y, exponent));
+        tree.chances[c].outcomes.push(Outcome {
+            x,
+            y,
+            exponent,
+            probability,
+            child,
+        });
+        child
+    }
+}
+
+impl<const N: usize> Agent<N> for MctsAgent<N> {
+    fn name(&self) -> String {
+        let leaf = match self.config.leaf {
+            LeafValue::Rollout(policy) => format!("{policy} rollouts"),
+            LeafValue::Critic => "critic".to_string(),
+        };
+        let selection = if self.config.priors { "PUCT" } else { "UCT" };
+        format!("mcts ({} sims, {selection}, {leaf})", self.config.simulations)
+    }
+
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        Ok(self.search(state).best_move())
+    }
+
+    fn evaluate(&mut self, state: &GameState<N>) -> MoveEvaluation {
+        self.search(state).evaluation()
+    }
+
+    fn choose_with_evaluation(&mut self, state: &GameState<N>) -> io::Result<(Move, MoveEvaluation)> {
+        let result = self.search(state);
+        Ok((result.best_move(), result.evaluation()))
+    }
+}
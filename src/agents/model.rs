@@ -0,0 +1,79 @@
+//! The neural network agent, which plays whatever move its `PolicyNet` likes best
+
+use std::io;
+
+use burn::backend::NdArray;
+use burn::backend::ndarray::NdArrayDevice;
+use burn::prelude::Backend;
+use burn::tensor::activation::softmax;
+
+use super::Agent;
+use super::MoveEvaluation;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::model_structs::PolicyNet;
+use crate::model_structs::PolicyNetConfig;
+use crate::model_traits::Model;
+use crate::model_traits::MoveResult;
+
+/// Plays by taking the highest-scoring legal move from a `PolicyNet`
+pub struct ModelAgent<const N: usize, B: Backend> {
+    model: PolicyNet<N, B>,
+    device: B::Device,
+    name: String,
+}
+
+impl<const N: usize, B: Backend> ModelAgent<N, B> {
+    /// Play with a model that's already loaded; `name` is what `Agent::name` reports
+    pub fn new(model: PolicyNet<N, B>, device: B::Device, name: impl Into<String>) -> Self {
+        Self {
+            model,
+            device,
+            name: name.into(),
+        }
+    }
+}
+
+impl<const N: usize, B: Backend> Agent<N> for ModelAgent<N, B> {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        let MoveResult { next_move, .. } = self.model.get_next_move(state, &self.device);
+        Ok(next_move)
+    }
+
+    fn evaluate(&mut self, state: &GameState<N>) -> MoveEvaluation {
+        let input = self.model.input_to_tensor(state, &self.device);
+        let (actor_logits, critic_value) = self.model.get_output_tensor(input);
+
+        let probs: Vec<f32> = softmax(actor_logits, 0)
+            .into_data()
+            .into_vec()
+            .expect("Should be able to convert to vec");
+        let value: Vec<f32> = critic_value.into_data().into_vec().expect("Should be able to convert to vec");
+
+        MoveEvaluation {
+            policy: Some([probs[0], probs[1], probs[2], probs[3]]),
+            value: Some(value[0]),
+            expected_scores: None,
+        }
+    }
+}
+
+impl<const N: usize> ModelAgent<N, NdArray> {
+    /// A freshly initialized (random) PolicyNet, on the CPU
+    pub fn untrained() -> Self {
+        let device = NdArrayDevice::default();
+        let model: PolicyNet<N, NdArray> = PolicyNetConfig::new().init(&device);
+        Self::new(model, device, "untrained PolicyNet")
+    }
+
+    /// A PolicyNet loaded from a checkpoint, on the CPU
+    pub fn load(path: &str) -> io::Result<Self> {
+        let device = NdArrayDevice::default();
+        let model: PolicyNet<N, NdArray> = PolicyNet::load(path, &device)?;
+        Ok(Self::new(model, device, format!("PolicyNet from {path}")))
+    }
+}
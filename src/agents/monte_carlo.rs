@@ -0,0 +1,338 @@
+//! Monte Carlo rollouts: judge a position by playing it out to the end many times with a cheap
+//! policy and averaging how the games finished. `MonteCarloAgent` plays by doing this for every
+//! legal move.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::AddRandomPiece;
+use crate::game_traits::FullGame;
+
+/// How moves are picked while playing a rollout out
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RolloutPolicy {
+    /// Any legal move, uniformly
+    #[default]
+    Random,
+    /// The legal move with the biggest immediate score gain, ties broken at random
+    Greedy,
+}
+
+impl FromStr for RolloutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "random" => Ok(RolloutPolicy::Random),
+            "greedy" => Ok(RolloutPolicy::Greedy),
+            other => Err(format!("Unknown rollout policy '{other}'; expected random or greedy")),
+        }
+    }
+}
+
+impl fmt::Display for RolloutPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RolloutPolicy::Random => write!(f, "random"),
+            RolloutPolicy::Greedy => write!(f, "greedy"),
+        }
+    }
+}
+
+impl RolloutPolicy {
+    /// Pick a move in an unfinished game
+    pub fn choose<const N: usize, R: Rng>(self, state: &GameState<N>, rng: &mut R) -> Move {
+        let legal: Vec<Move> = Move::ALL.into_iter().filter(|&m| state.is_legal_move(m)).collect();
+        let candidates = match self {
+            RolloutPolicy::Random => legal,
+            RolloutPolicy::Greedy => {
+                let gain = |m: Move| state.slide(m).current_score();
+                let best = legal.iter().map(|&m| gain(m)).max().expect("Only called on unfinished games");
+                legal.into_iter().filter(|&m| gain(m) == best).collect()
+            }
+        };
+        candidates[rng.random_range(0..candidates.len())]
+    }
+}
+
+/// Play `state` out to the end with `policy`, and return the final position
+pub fn rollout<const N: usize, R: Rng>(
+    state: &GameState<N>,
+    policy: RolloutPolicy,
+    spawns: &mut RngPlacement,
+    rng: &mut R,
+) -> GameState<N> {
+    let mut state = *state;
+    while !state.is_finished() {
+        let m = policy.choose(&state, rng);
+        state = state.apply_move(m, spawns).expect("Policies only pick legal moves");
+    }
+    state
+}
+
+/// Play an afterstate (a board just after a move, before its piece spawns) out to the end, with
+/// pieces spawning by `spawn_policy`. The seed fixes both the spawns and the policy's choices.
+pub fn rollout_from_afterstate<const N: usize>(
+    afterstate: &GameState<N>,
+    policy: RolloutPolicy,
+    spawn_policy: SpawnPolicy,
+    seed: u64,
+) -> GameState<N> {
+    let mut spawns = RngPlacement::new_from_seed(seed).with_spawn_policy(spawn_policy);
+    let mut rng = policy_rng(seed);
+    let start = spawns.next_piece(afterstate);
+    rollout(&start, policy, &mut spawns, &mut rng)
+}
+
+/// Mixed into a rollout's seed for its policy's choices. `RngPlacement` and `StdRng` are the same
+/// generator, so seeding both with the same value would make the moves follow the spawns.
+const POLICY_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Where a rollout seeded with `seed` gets its moves from; a different stream from its spawns
+pub(super) fn policy_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed ^ POLICY_SEED_SALT)
+}
+
+/// Final positions of `rollouts` games played out from an afterstate. The same seed gives the
+/// same games, so different afterstates can be compared fairly.
+pub fn rollouts_from_afterstate<const N: usize>(
+    afterstate: &GameState<N>,
+    policy: RolloutPolicy,
+    spawn_policy: SpawnPolicy,
+    rollouts: usize,
+    seed: u64,
+) -> Vec<GameState<N>> {
+    (0..rollouts)
+        .map(|i| rollout_from_afterstate(afterstate, policy, spawn_policy, seed.wrapping_add(i as u64)))
+        .collect()
+}
+
+/// Mean final score of `rollouts` games played out from an afterstate; see
+/// `rollouts_from_afterstate`
+pub fn mean_rollout_score<const N: usize>(
+    afterstate: &GameState<N>,
+    policy: RolloutPolicy,
+    spawn_policy: SpawnPolicy,
+    rollouts: usize,
+    seed: u64,
+) -> f32 {
+    let finals = rollouts_from_afterstate(afterstate, policy, spawn_policy, rollouts, seed);
+    let total: f64 = finals.iter().map(|state| state.current_score() as f64).sum();
+    (total / finals.len().max(1) as f64) as f32
+}
+
+/// What the Monte Carlo agent tries to maximize over its rollouts
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RolloutObjective {
+    /// Mean final score
+    #[default]
+    MeanScore,
+    /// Fraction of games that reach this tile (e.g. 2048), with the mean score breaking ties
+    TileRate(u32),
+}
+
+/// Settings for `MonteCarloAgent`. On the command line these are written as comma-separated
+/// `key=value` pairs, e.g. `rollouts=200,policy=greedy,ms=50,objective=2048`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MonteCarloConfig {
+    /// Games played out after each legal move
+    pub rollouts: usize,
+    /// How rollouts pick moves
+    pub policy: RolloutPolicy,
+    /// Stop early once this much time has gone into a move (after at least one round)
+    pub time_budget: Option<Duration>,
+    /// What the chosen move should do best at
+    pub objective: RolloutObjective,
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self {
+            rollouts: 100,
+            policy: RolloutPolicy::Random,
+            time_budget: None,
+            objective: RolloutObjective::MeanScore,
+        }
+    }
+}
+
+impl FromStr for MonteCarloConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = MonteCarloConfig::default();
+        for option in s.split(',').filter(|option| !option.is_empty()) {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(format!("Expected key=value, got '{option}'"));
+            };
+            let invalid = || format!("Invalid value '{value}' for {key}");
+            match key {
+                "rollouts" => {
+                    config.rollouts = match value.parse() {
+                        Ok(rollouts) if rollouts > 0 => rollouts,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "policy" => config.policy = value.parse()?,
+                "ms" => config.time_budget = Some(Duration::from_millis(value.parse().map_err(|_| invalid())?)),
+                "objective" => {
+                    config.objective = match value {
+                        "score" => RolloutObjective::MeanScore,
+                        tile => match tile.parse::<u32>() {
+                            Ok(tile) if tile.is_power_of_two() && tile > 1 => RolloutObjective::TileRate(tile),
+                            _ => return Err(invalid()),
+                        },
+                    }
+                }
+                _ => {
+                    return Err(format!(
+                        "Unknown Monte Carlo option '{key}'; expected rollouts, policy, ms or objective"
+                    ));
+                }
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// How the rollouts after one move turned out
+#[derive(Copy, Clone, Debug, Default)]
+struct RolloutTally {
+    games: usize,
+    total_score: f64,
+    /// Games that reached the objective's tile, if it has one
+    reached: usize,
+}
+
+impl RolloutTally {
+    fn mean_score(&self) -> f64 {
+        self.total_score / self.games.max(1) as f64
+    }
+
+    /// What the objective ranks moves by, then the tie-breaker
+    fn rank(&self, objective: RolloutObjective) -> (f64, f64) {
+        match objective {
+            RolloutObjective::MeanScore => (self.mean_score(), 0.0),
+            RolloutObjective::TileRate(_) => (self.reached as f64 / self.games.max(1) as f64, self.mean_score()),
+        }
+    }
+}
+
+/// Plays each legal move out many times with a cheap policy, and takes the one whose games
+/// turned out best
+pub struct MonteCarloAgent {
+    config: MonteCarloConfig,
+    spawn_policy: SpawnPolicy,
+    /// Each round of rollouts gets a fresh seed from here, shared by every move
+    seeds: StdRng,
+}
+
+impl MonteCarloAgent {
+    /// An agent whose rollouts are seeded at random; see `with_seed`
+    pub fn new(config: MonteCarloConfig) -> Self {
+        Self {
+            config,
+            spawn_policy: SpawnPolicy::default(),
+            seeds: StdRng::from_os_rng(),
+        }
+    }
+
+    /// Play out games whose pieces spawn this way
+    pub fn with_spawn_policy(self, spawn_policy: SpawnPolicy) -> Self {
+        Self { spawn_policy, ..self }
+    }
+
+    /// Make the rollouts, and so the agent's choices, repeatable
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seeds: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+
+    /// Rollout results for each legal move, indexed by `Move::to_idx`. Rollouts go in rounds of
+    /// one game per move, so an early stop leaves every move with the same number of games.
+    fn tallies<const N: usize>(&mut self, state: &GameState<N>) -> [Option<RolloutTally>; 4] {
+        let start = Instant::now();
+        let afterstates = Move::ALL.map(|m| state.is_legal_move(m).then(|| state.slide(m)));
+        let mut tallies = afterstates.map(|afterstate| afterstate.map(|_| RolloutTally::default()));
+
+        for round in 0..self.config.rollouts.max(1) {
+            if round > 0 && self.config.time_budget.is_some_and(|budget| start.elapsed() >= budget) {
+                break;
+            }
+
+            let seed = self.seeds.random();
+            for (afterstate, tally) in afterstates.iter().zip(tallies.iter_mut()) {
+                if let (Some(afterstate), Some(tally)) = (afterstate, tally) {
+                    let end = rollout_from_afterstate(afterstate, self.config.policy, self.spawn_policy, seed);
+                    tally.games += 1;
+                    tally.total_score += end.current_score() as f64;
+                    if let RolloutObjective::TileRate(tile) = self.config.objective
+                        && end.highest_tile() >= tile
+                    {
+                        tally.reached += 1;
+                    }
+                }
+            }
+        }
+
+        tallies
+    }
+}
+
+/// The legal move whose rollouts ranked best under `objective`
+fn best_move(tallies: &[Option<RolloutTally>; 4], objective: RolloutObjective) -> Move {
+    Move::ALL
+        .into_iter()
+        .filter_map(|m| tallies[m.to_idx()].map(|tally| (m, tally.rank(objective))))
+        .reduce(|best, next| if next.1 > best.1 { next } else { best })
+        .expect("Should only be asked to move in unfinished games")
+        .0
+}
+
+fn evaluation(tallies: &[Option<RolloutTally>; 4]) -> MoveEvaluation {
+    MoveEvaluation {
+        expected_scores: Some(tallies.map(|tally| tally.map(|tally| tally.mean_score() as f32))),
+        ..Default::default()
+    }
+}
+
+impl<const N: usize> Agent<N> for MonteCarloAgent {
+    fn name(&self) -> String {
+        let mut name = format!("monte carlo ({} {} rollouts", self.config.rollouts, self.config.policy);
+        if let Some(budget) = self.config.time_budget {
+            name += &format!(", {}ms", budget.as_millis());
+        }
+        if let RolloutObjective::TileRate(tile) = self.config.objective {
+            name += &format!(", for {tile}");
+        }
+        name + ")"
+    }
+
+    fn choose_move(&mut self, state: &GameState<N>) -> io::Result<Move> {
+        Ok(best_move(&self.tallies(state), self.config.objective))
+    }
+
+    fn evaluate(&mut self, state: &GameState<N>) -> MoveEvaluation {
+        evaluation(&self.tallies(state))
+    }
+
+    fn choose_with_evaluation(&mut self, state: &GameState<N>) -> io::Result<(Move, MoveEvaluation)> {
+        let tallies = self.tallies(state);
+        Ok((best_move(&tallies, self.config.objective), evaluation(&tallies)))
+    }
+}
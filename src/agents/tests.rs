@@ -0,0 +1,374 @@
+use std::io;
+use std::time::Duration;
+use std::time::Instant;
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::agents::Agent;
+use crate::agents::AgentSpec;
+use crate::agents::ChanceNodes;
+use crate::agents::ExpectimaxAgent;
+use crate::agents::LeafValue;
+use crate::agents::MctsAgent;
+use crate::agents::MctsConfig;
+use crate::agents::MonteCarloAgent;
+use crate::agents::MonteCarloConfig;
+use crate::agents::MoveEvaluation;
+use crate::agents::RolloutObjective;
+use crate::agents::RolloutPolicy;
+use crate::agents::monte_carlo::policy_rng;
+use crate::agents::rollouts_from_afterstate;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+
+#[test]
+fn test_expectimax_takes_the_big_merge() {
+    // merging the two 256s (up or down) is worth far more than anything else
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [8, 1, 2, 1],
+            [8, 2, 1, 2],
+            [1, 3, 2, 1],
+            [2, 1, 3, 2],
+        ],
+        0,
+    );
+
+    let mut agent = ExpectimaxAgent::new(1);
+    let chosen = agent.choose_move(&state).unwrap();
+    assert!(chosen == Move::Up || chosen == Move::Down, "chose {chosen:?}");
+
+    let values = agent.move_values(&state);
+    assert!(values[Move::Left.to_idx()].is_none(), "left is illegal");
+    assert!(values[Move::Up.to_idx()].unwrap() >= 512.0);
+}
+
+#[test]
+fn test_expectimax_weighs_spawns_by_policy() {
+    // a lone 4: the second move can only score by merging it with a spawned 4, which classic
+    // games spawn far less often
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [2, 0, 0, 0],
+            [0, 0, 0, 0],
+            [0, 0, 0, 0],
+            [0, 0, 0, 0],
+        ],
+        0,
+    );
+
+    let even = ExpectimaxAgent::new(2).move_values(&state)[Move::Down.to_idx()].unwrap();
+    let classic = ExpectimaxAgent::new(2).with_spawn_policy(SpawnPolicy::Classic).move_values(&state)[Move::Down.to_idx()].unwrap();
+    assert!(even > classic, "even {even} should beat classic {classic}");
+}
+
+#[test]
+fn test_mcts_spawns_by_policy() {
+    // right leaves one gap, between two 4s: the game only goes on if a 4 spawns there
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [3, 4, 3],
+            [2, 3, 4],
+            [1, 1, 5],
+        ],
+        0,
+    );
+    let right_value = |spawn_policy, chance| {
+        let config = MctsConfig {
+            simulations: 400,
+            chance,
+            spawn_policy,
+            ..Default::default()
+        };
+        let result = MctsAgent::new(config, None).with_seed(3).search(&state);
+        result.values[Move::Right.to_idx()].unwrap()
+    };
+
+    for chance in [ChanceNodes::Sampled, ChanceNodes::Widened { k: 1.0, alpha: 0.5 }] {
+        let even = right_value(SpawnPolicy::Even, chance);
+        let classic = right_value(SpawnPolicy::Classic, chance);
+        assert!(even > classic, "{chance:?}: even {even} should beat classic {classic}");
+    }
+}
+
+#[test]
+fn test_agent_spec_parsing() {
+    assert!(matches!("untrained".parse(), Ok(AgentSpec::Untrained)));
+    assert!(matches!("expectimax".parse(), Ok(AgentSpec::Expectimax(2))));
+    assert!(matches!("expectimax:3".parse(), Ok(AgentSpec::Expectimax(3))));
+    assert!(matches!("model:foo.bin".parse(), Ok(AgentSpec::Model(path)) if path == "foo.bin"));
+
+    assert!(matches!("mc".parse(), Ok(AgentSpec::MonteCarlo(config)) if config == MonteCarloConfig::default()));
+    let Ok(AgentSpec::MonteCarlo(config)) = "mc:rollouts=20,policy=greedy,ms=50,objective=2048".parse() else {
+        panic!("should parse");
+    };
+    assert_eq!(
+        config,
+        MonteCarloConfig {
+            rollouts: 20,
+            policy: RolloutPolicy::Greedy,
+            time_budget: Some(Duration::from_millis(50)),
+            objective: RolloutObjective::TileRate(2048),
+        }
+    );
+
+    let Ok(AgentSpec::Mcts(config, None)) = "mcts:sims=50,leaf=greedy,widen=0.5".parse() else {
+        panic!("should parse");
+    };
+    assert_eq!(config.simulations, 50);
+    assert_eq!(config.leaf, LeafValue::Rollout(RolloutPolicy::Greedy));
+    assert_eq!(config.chance, ChanceNodes::Widened { k: 1.0, alpha: 0.5 });
+    let Ok(AgentSpec::Mcts(config, None)) = "mcts:widen_k=2,widen=0.25".parse() else {
+        panic!("should parse");
+    };
+    assert_eq!(config.chance, ChanceNodes::Widened { k: 2.0, alpha: 0.25 });
+    assert!(matches!(
+        "mcts:leaf=critic,priors=true,model=net.bin".parse(),
+        Ok(AgentSpec::Mcts(_, Some(path))) if path == "net.bin"
+    ));
+
+    assert!("model".parse::<AgentSpec>().is_err());
+    assert!("mcts:priors=true".parse::<AgentSpec>().is_err(), "priors need a model");
+    for bad in [
+        "widen=nan",
+        "widen=0",
+        "widen=-0.5",
+        "widen=inf",
+        "widen_k=2",
+        "widen=0.5,widen_k=0",
+        "c=nan",
+        "c=-1",
+        "c=0",
+    ] {
+        assert!(format!("mcts:{bad}").parse::<AgentSpec>().is_err(), "{bad} should not parse");
+    }
+    assert!("mc:rollouts=many".parse::<AgentSpec>().is_err());
+    assert!("mc:rollouts=0".parse::<AgentSpec>().is_err());
+    assert!("mcts:sims=0".parse::<AgentSpec>().is_err());
+    let Err(unknown) = "mcts:depth=3".parse::<AgentSpec>() else {
+        panic!("depth isn't an MCTS option");
+    };
+    assert!(unknown.ends_with("widen_k or model"), "{unknown}");
+    assert!("depth=3".parse::<MctsConfig>().unwrap_err().ends_with("widen or widen_k"));
+    assert!("mc:objective=100".parse::<AgentSpec>().is_err());
+    assert!("expectimax:deep".parse::<AgentSpec>().is_err());
+    assert!("alphago".parse::<AgentSpec>().is_err());
+}
+
+#[test]
+fn test_greedy_rollout_policy() {
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [8, 1, 2, 1],
+            [8, 2, 1, 2],
+            [1, 3, 2, 1],
+            [2, 1, 3, 2],
+        ],
+        0,
+    );
+
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..10 {
+        let m = RolloutPolicy::Greedy.choose(&state, &mut rng);
+        assert!(m == Move::Up || m == Move::Down, "chose {m:?}");
+    }
+
+    assert_eq!("greedy".parse(), Ok(RolloutPolicy::Greedy));
+    assert!("smart".parse::<RolloutPolicy>().is_err());
+}
+
+#[test]
+fn test_rollouts_play_to_the_end() {
+    let afterstate = GameState::<3>::from_grid([[1, 0, 0], [0, 2, 0], [0, 0, 0]], 0);
+
+    let finals = rollouts_from_afterstate(&afterstate, RolloutPolicy::Random, SpawnPolicy::Even, 20, 5);
+    assert_eq!(finals.len(), 20);
+    assert!(finals.iter().all(|state| state.is_finished()));
+    // different games, but the same ones every time for a given seed
+    assert!(finals.iter().any(|state| state != &finals[0]));
+    assert_eq!(
+        rollouts_from_afterstate(&afterstate, RolloutPolicy::Random, SpawnPolicy::Even, 20, 5),
+        finals
+    );
+}
+
+#[test]
+fn test_rollout_moves_and_spawns_use_different_streams() {
+    for seed in [0, 5, 1234] {
+        let mut spawns = ChaCha12Rng::seed_from_u64(seed);
+        let mut moves = policy_rng(seed);
+        let spawn_words: Vec<u32> = (0..8).map(|_| spawns.random()).collect();
+        let move_words: Vec<u32> = (0..8).map(|_| moves.random()).collect();
+        assert_ne!(spawn_words, move_words, "seed {seed}");
+    }
+}
+
+#[test]
+fn test_rollouts_spawn_by_policy() {
+    // one empty corner, and nothing merges whatever spawns there, so each game is one spawn long
+    let afterstate = GameState::<3>::from_grid([[3, 4, 3], [4, 3, 4], [3, 4, 0]], 0);
+    let fours = |spawn_policy| {
+        rollouts_from_afterstate(&afterstate, RolloutPolicy::Random, spawn_policy, 400, 11)
+            .iter()
+            .filter(|state| state.get_val(2, 2) == 2)
+            .count()
+    };
+
+    let even = fours(SpawnPolicy::Even);
+    let classic = fours(SpawnPolicy::Classic);
+    assert!((150..250).contains(&even), "{even} of 400 were 4s");
+    assert!((15..70).contains(&classic), "{classic} of 400 were 4s");
+}
+
+#[test]
+fn test_monte_carlo_takes_the_big_merge() {
+    let state = big_merge_state();
+
+    for policy in [RolloutPolicy::Random, RolloutPolicy::Greedy] {
+        let config = MonteCarloConfig {
+            rollouts: 20,
+            policy,
+            ..Default::default()
+        };
+        let mut agent = MonteCarloAgent::new(config).with_seed(1);
+        let chosen = agent.choose_move(&state).unwrap();
+        assert!(chosen == Move::Up || chosen == Move::Down, "{policy} chose {chosen:?}");
+
+        let scores = agent.evaluate(&state).expected_scores.unwrap();
+        assert!(scores[Move::Left.to_idx()].is_none(), "left is illegal");
+        assert!(scores[Move::Up.to_idx()].unwrap() >= 512.0);
+    }
+}
+
+#[test]
+fn test_monte_carlo_time_budget() {
+    let config = MonteCarloConfig {
+        rollouts: usize::MAX,
+        time_budget: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let mut agent = MonteCarloAgent::new(config).with_seed(2);
+    let state = GameState::<4>::from_grid([[1, 1, 0, 0], [0; 4], [0; 4], [0; 4]], 0);
+
+    let start = Instant::now();
+    let _ = agent.choose_move(&state).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+/// The big merge from `test_expectimax_takes_the_big_merge`
+fn big_merge_state() -> GameState<4> {
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [8, 1, 2, 1],
+            [8, 2, 1, 2],
+            [1, 3, 2, 1],
+            [2, 1, 3, 2],
+        ],
+        0,
+    );
+    state
+}
+
+#[test]
+fn test_mcts_takes_the_big_merge() {
+    let state = big_merge_state();
+
+    for chance in [ChanceNodes::Sampled, ChanceNodes::Widened { k: 1.0, alpha: 0.5 }] {
+        let config = MctsConfig {
+            simulations: 100,
+            chance,
+            ..Default::default()
+        };
+        let mut agent = MctsAgent::new(config, None).with_seed(4);
+        let result = agent.search(&state);
+
+        assert_eq!(result.visits.iter().sum::<u32>(), 100);
+        assert_eq!(result.visits[Move::Left.to_idx()], 0, "left is illegal");
+        assert!(result.values[Move::Left.to_idx()].is_none());
+        let best = result.best_move();
+        assert!(best == Move::Up || best == Move::Down, "{chance:?} chose {best:?}");
+        assert!(result.values[best.to_idx()].unwrap() >= 512.0);
+    }
+}
+
+/// Always wants to go up, and thinks nothing more will be scored
+struct UpModel;
+
+impl Agent<4> for UpModel {
+    fn name(&self) -> String {
+        "up".to_string()
+    }
+
+    fn choose_move(&mut self, _state: &GameState<4>) -> io::Result<Move> {
+        Ok(Move::Up)
+    }
+
+    fn evaluate(&mut self, _state: &GameState<4>) -> MoveEvaluation {
+        MoveEvaluation {
+            policy: Some([0.97, 0.01, 0.01, 0.01]),
+            value: Some(0.0),
+            expected_scores: None,
+        }
+    }
+}
+
+#[test]
+fn test_mcts_priors_steer_the_search() {
+    // every move is legal and, by the critic, worth the same, so only the priors matter
+    let state = GameState::<4>::from_grid([[0, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]], 0);
+    let config = MctsConfig {
+        simulations: 50,
+        leaf: LeafValue::Critic,
+        priors: true,
+        ..Default::default()
+    };
+    let mut agent = MctsAgent::new(config, Some(Box::new(UpModel))).with_seed(0);
+
+    let result = agent.search(&state);
+    assert_eq!(result.best_move(), Move::Up);
+    assert!(result.visit_distribution()[Move::Up.to_idx()] > 0.8);
+}
+
+#[test]
+fn test_mcts_time_budget() {
+    let config = MctsConfig {
+        simulations: usize::MAX,
+        time_budget: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
+    let mut agent = MctsAgent::new(config, None).with_seed(2);
+
+    let start = Instant::now();
+    let result = agent.search(&GameState::<4>::from_grid([[1, 1, 0, 0], [0; 4], [0; 4], [0; 4]], 0));
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(result.visits.iter().sum::<u32>() > 0);
+}
+
+#[test]
+fn test_choose_with_evaluation_searches_once() {
+    let state = big_merge_state();
+    let config = MctsConfig {
+        simulations: 60,
+        ..Default::default()
+    };
+
+    let (m, evaluation) = MctsAgent::new(config, None).with_seed(5).choose_with_evaluation(&state).unwrap();
+    // same seed, same search: the move and the evaluation describe the search that was run
+    assert_eq!(m, MctsAgent::new(config, None).with_seed(5).choose_move(&state).unwrap());
+    let policy = evaluation.policy.unwrap();
+    assert!(Move::ALL.into_iter().all(|other| policy[other.to_idx()] <= policy[m.to_idx()]));
+
+    let (m, evaluation) = ExpectimaxAgent::new(1).choose_with_evaluation(&state).unwrap();
+    assert!(m == Move::Up || m == Move::Down, "chose {m:?}");
+    assert_eq!(evaluation.expected_scores, Some(ExpectimaxAgent::new(1).move_values(&state)));
+}
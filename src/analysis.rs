@@ -0,0 +1,161 @@
+//! Everything we know about each move from one position, side by side: what it does to the
+//! board, what a model thinks of it, and how search and rollouts rate it. Mostly for working out
+//! why an agent made a bad decision.
+
+use serde::Serialize;
+
+use crate::agents::Agent;
+use crate::agents::ExpectimaxAgent;
+use crate::agents::RolloutPolicy;
+use crate::agents::mean_rollout_score;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+use crate::notation::Position;
+
+#[cfg(test)]
+mod tests;
+
+/// How hard to look at each move
+#[derive(Copy, Clone, Debug)]
+pub struct AnalysisOptions {
+    /// Expectimax search depth
+    pub depth: usize,
+    /// Number of games played out after each move
+    pub rollouts: usize,
+    /// How the rollouts pick moves
+    pub rollout_policy: RolloutPolicy,
+    /// Seed for the rollouts; every move gets the same one, so they're compared fairly
+    pub seed: u64,
+    /// How pieces spawn in the game being analyzed
+    pub spawn_policy: SpawnPolicy,
+}
+
+/// Everything `analyze` found out about a position
+#[derive(Clone, Debug, Serialize)]
+pub struct PositionAnalysis {
+    /// The position, in board notation
+    pub position: String,
+    /// The position's score
+    pub score: u32,
+    /// The model's critic value for the position itself, if a model was given
+    pub value: Option<f32>,
+    /// One entry per move, in `Move::ALL` order
+    pub moves: Vec<MoveAnalysis>,
+}
+
+/// What each source says about one move. Everything but `legal` is missing for illegal moves,
+/// and `policy` and `critic` are missing without a model.
+#[derive(Clone, Debug, Serialize)]
+pub struct MoveAnalysis {
+    /// The move being looked at
+    #[serde(rename = "move")]
+    pub m: Move,
+    /// Whether the move changes the board
+    pub legal: bool,
+    /// The board after sliding, before the new piece spawns, in board notation
+    pub afterstate: Option<String>,
+    /// Points scored by the move's merges
+    pub gain: Option<u32>,
+    /// The model's probability of picking the move
+    pub policy: Option<f32>,
+    /// The model's critic value for the afterstate
+    pub critic: Option<f32>,
+    /// Expected score after searching `depth` moves ahead
+    pub expectimax: Option<f32>,
+    /// Mean final score of the rollouts
+    pub rollout_mean: Option<f32>,
+}
+
+/// Look at every move from `game`, with `model` (if given) supplying the policy and critic
+pub fn analyze<const N: usize>(
+    game: &GameState<N>,
+    options: &AnalysisOptions,
+    mut model: Option<&mut Box<dyn Agent<N>>>,
+) -> PositionAnalysis {
+    let evaluation = model.as_mut().map(|model| model.evaluate(game)).unwrap_or_default();
+    let expectimax = ExpectimaxAgent::new(options.depth)
+        .with_spawn_policy(options.spawn_policy)
+        .move_values(game);
+
+    let moves = Move::ALL
+        .into_iter()
+        .map(|m| {
+            if !game.is_legal_move(m) {
+                return MoveAnalysis {
+                    m,
+                    legal: false,
+                    afterstate: None,
+                    gain: None,
+                    policy: None,
+                    critic: None,
+                    expectimax: None,
+                    rollout_mean: None,
+                };
+            }
+
+            let afterstate = game.slide(m);
+            MoveAnalysis {
+                m,
+                legal: true,
+                afterstate: Some(Position::from_state(&afterstate).to_string()),
+                gain: Some(afterstate.current_score() - game.current_score()),
+                policy: evaluation.policy.map(|policy| policy[m.to_idx()]),
+                critic: model.as_mut().and_then(|model| model.evaluate(&afterstate).value),
+                expectimax: expectimax[m.to_idx()],
+                rollout_mean: Some(mean_rollout_score(
+                    &afterstate,
+                    options.rollout_policy,
+                    options.spawn_policy,
+                    options.rollouts,
+                    options.seed,
+                )),
+            }
+        })
+        .collect();
+
+    PositionAnalysis {
+        position: Position::from_state(game).to_string(),
+        score: game.current_score(),
+        value: evaluation.value,
+        moves,
+    }
+}
+
+fn cell(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+fn cell_f32(value: Option<f32>, decimals: usize) -> String {
+    cell(value.map(|v| format!("{v:.decimals$}")))
+}
+
+impl PositionAnalysis {
+    /// Human-readable table, one row per move
+    pub fn table_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("Position: {} (score {})", self.position, self.score)];
+        if let Some(value) = self.value {
+            lines.push(format!("Critic value: {value:.1}"));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "{:<6} {:<5} {:>6} {:>7} {:>9} {:>11} {:>9}  Afterstate",
+            "Move", "Legal", "Gain", "Policy", "Critic", "Expectimax", "Rollouts"
+        ));
+        for m in &self.moves {
+            lines.push(format!(
+                "{:<6} {:<5} {:>6} {:>7} {:>9} {:>11} {:>9}  {}",
+                format!("{:?}", m.m),
+                if m.legal { "yes" } else { "no" },
+                cell(m.gain),
+                cell_f32(m.policy, 3),
+                cell_f32(m.critic, 1),
+                cell_f32(m.expectimax, 1),
+                cell_f32(m.rollout_mean, 1),
+                cell(m.afterstate.as_deref()),
+            ));
+        }
+        lines
+    }
+}
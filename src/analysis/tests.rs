@@ -0,0 +1,101 @@
+use std::io;
+
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
+use crate::agents::RolloutPolicy;
+use crate::analysis::AnalysisOptions;
+use crate::analysis::analyze;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+
+/// Likes up, and values a board at its score
+struct FakeModel;
+
+impl Agent<4> for FakeModel {
+    fn name(&self) -> String {
+        "fake".to_string()
+    }
+
+    fn choose_move(&mut self, _state: &GameState<4>) -> io::Result<Move> {
+        Ok(Move::Up)
+    }
+
+    fn evaluate(&mut self, state: &GameState<4>) -> MoveEvaluation {
+        MoveEvaluation {
+            policy: Some([0.7, 0.1, 0.1, 0.1]),
+            value: Some(state.current_score() as f32),
+            expected_scores: None,
+        }
+    }
+}
+
+fn options() -> AnalysisOptions {
+    AnalysisOptions {
+        depth: 1,
+        rollouts: 10,
+        rollout_policy: RolloutPolicy::Random,
+        seed: 3,
+        spawn_policy: SpawnPolicy::Even,
+    }
+}
+
+#[test]
+fn test_analyze_position() {
+    // everything is already against the left wall, so left is illegal; the 2s merge vertically
+    #[rustfmt::skip]
+    let game = GameState::from_grid(
+        [
+            [1, 0, 0, 0],
+            [1, 0, 0, 0],
+            [3, 0, 0, 0],
+            [0, 0, 0, 0],
+        ],
+        16,
+    );
+
+    let analysis = analyze(&game, &options(), None);
+    assert_eq!(analysis.position, "2,.,.,./2,.,.,./8,.,.,./.,.,.,.:16");
+    assert_eq!(analysis.value, None);
+    assert_eq!(analysis.moves.len(), 4);
+
+    let up = &analysis.moves[Move::Up.to_idx()];
+    assert!(up.legal);
+    assert_eq!(up.gain, Some(4));
+    assert_eq!(up.afterstate.as_deref(), Some("4,.,.,./8,.,.,./.,.,.,./.,.,.,.:20"));
+    assert!(up.expectimax.unwrap() >= 20.0);
+    assert!(up.rollout_mean.unwrap() >= 20.0);
+    assert_eq!(up.policy, None);
+
+    let left = &analysis.moves[Move::Left.to_idx()];
+    assert!(!left.legal);
+    assert!(left.afterstate.is_none() && left.gain.is_none() && left.rollout_mean.is_none());
+
+    // seeded, so the same every time
+    let again = analyze(&game, &options(), None);
+    assert_eq!(again.moves[0].rollout_mean, up.rollout_mean);
+
+    let lines = analysis.table_lines();
+    assert_eq!(lines.len(), 7);
+    assert!(lines[3].starts_with("Up     yes        4"));
+    assert!(lines[5].starts_with("Left   no         -"));
+}
+
+#[test]
+fn test_analyze_with_model() {
+    let game = GameState::<4>::from_grid([[1, 1, 0, 0], [0; 4], [0; 4], [0; 4]], 0);
+
+    let mut model: Box<dyn Agent<4>> = Box::new(FakeModel);
+    let analysis = analyze(&game, &options(), Some(&mut model));
+    assert_eq!(analysis.value, Some(0.0));
+
+    let left = &analysis.moves[Move::Left.to_idx()];
+    assert_eq!(left.policy, Some(0.1));
+    // the critic judges the afterstate, which has the merge's points
+    assert_eq!(left.critic, Some(4.0));
+
+    let json = serde_json::to_value(&analysis).unwrap();
+    assert_eq!(json["moves"][2]["move"], "left");
+    assert_eq!(json["moves"][2]["gain"], 4);
+}
@@ -1,5 +1,18 @@
 //! Clap CLI for dispatching what we're gonna do
 
+use ai2048::SUPPORTED_SIZES;
+use ai2048::agents::AgentSpec;
+use ai2048::agents::MctsConfig;
+use ai2048::agents::RolloutPolicy;
+use ai2048::game_structs::Move;
+use ai2048::game_structs::SpawnPolicy;
+use ai2048::notation::Position;
+use ai2048::rewards::RewardWeights;
+use ai2048::training::AlphaZeroConfig;
+use ai2048::training::TrainingMode;
+use ai2048::tui::ThemeChoice;
+use ai2048::unsupported_size_message;
+use clap::Args;
 use clap::Parser;
 use clap::Subcommand;
 
@@ -8,6 +21,23 @@ use clap::Subcommand;
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// Board size N for an NxN game (3 to 8); defaults to 4, or to the recording's size for replay
+    #[arg(long, global = true, value_parser = parse_board_size)]
+    pub size: Option<usize>,
+
+    /// Tile colors: auto, ansi, truecolor, mono, or high-contrast
+    #[arg(long, global = true, default_value = "auto")]
+    pub theme: ThemeChoice,
+}
+
+fn parse_board_size(s: &str) -> Result<usize, String> {
+    let size: usize = s.parse().map_err(|_| format!("Invalid board size '{s}'"))?;
+    if SUPPORTED_SIZES.contains(&size) {
+        Ok(size)
+    } else {
+        Err(unsupported_size_message(size))
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -17,13 +47,189 @@ pub enum Commands {
         /// Optional seed for the PRNG
         #[arg(short, long)]
         seed: Option<u64>,
+
+        /// Agent to ask for hints (press h): untrained, model:<path>, expectimax[:<depth>], mc[:<options>],
+        /// or mcts[:<options>]
+        #[arg(short, long)]
+        agent: Option<AgentSpec>,
+
+        /// Save a recording of the game to this file
+        #[arg(long)]
+        record: Option<String>,
+
+        /// How new pieces spawn: even (50% 2s) or classic (90% 2s)
+        #[arg(long, default_value = "even")]
+        spawn: SpawnPolicy,
+
+        /// Where to keep high scores and stats; see the stats command for the default
+        #[arg(long)]
+        stats_file: Option<String>,
+
+        /// Continue a game saved with S (its seed and spawn policy are used instead of --seed and --spawn)
+        #[arg(long, conflicts_with = "from")]
+        resume: Option<String>,
+
+        /// Start from this position instead of a random piece, e.g. "2,4,.,./.,.,8,./.,.,.,./.,16,.,2:60"
+        /// (rows split by /, . for empty, optional :score, which is estimated if left off)
+        #[arg(long)]
+        from: Option<Position>,
+
+        /// Where S saves the game
+        #[arg(long, default_value = "2048-save.json")]
+        save_file: String,
     },
 
-    /// Play the game automatically in the terminal, using an untrained model
+    /// Play the game automatically in the terminal
     AutoPlay {
         /// Optional seed for the PRNG
         #[arg(short, long)]
         seed: Option<u64>,
+
+        /// Agent to play with: untrained, model:<path>, expectimax[:<depth>], mc[:<options>] (Monte
+        /// Carlo rollouts, e.g. mc:rollouts=200,policy=greedy,ms=50,objective=2048), mcts[:<options>]
+        /// (tree search, e.g. mcts:sims=400,ms=100,leaf=critic,priors=true,model=model.bin), or
+        /// external:<command> (a program speaking the engine protocol; its stderr is discarded)
+        #[arg(short, long, default_value = "untrained")]
+        agent: AgentSpec,
+
+        /// Save a recording of the game to this file
+        #[arg(long)]
+        record: Option<String>,
+
+        /// How new pieces spawn: even (50% 2s) or classic (90% 2s)
+        #[arg(long, default_value = "even")]
+        spawn: SpawnPolicy,
+
+        /// Start from this position instead of a random piece; see play --help for the notation
+        #[arg(long)]
+        from: Option<Position>,
+    },
+
+    /// Step through a recorded game
+    Replay {
+        /// Recording saved with --record
+        file: String,
+    },
+
+    /// Evaluate every move from a position: legality, the board it leaves, points gained, a model's
+    /// policy and critic, expectimax, and Monte Carlo rollouts
+    Analyze {
+        /// Position to analyze; see play --help for the notation
+        #[arg(long)]
+        board: Position,
+
+        /// Model checkpoint for the policy and critic columns
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Expectimax search depth
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+
+        /// Games played out after each move
+        #[arg(long, default_value_t = 200)]
+        rollouts: usize,
+
+        /// How rollouts pick moves: random or greedy (biggest immediate gain)
+        #[arg(long, default_value = "random")]
+        rollout_policy: RolloutPolicy,
+
+        /// Seed for the rollouts
+        #[arg(short, long, default_value_t = 0)]
+        seed: u64,
+
+        /// How new pieces spawn: even (50% 2s) or classic (90% 2s)
+        #[arg(long, default_value = "even")]
+        spawn: SpawnPolicy,
+
+        /// Print JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Turn a recorded game into an asciinema recording (.cast), drawn in the --theme colors
+    Cast {
+        /// Recording saved with --record
+        file: String,
+
+        /// File to write
+        #[arg(short, long, default_value = "replay.cast")]
+        out: String,
+
+        /// Milliseconds between moves
+        #[arg(long, default_value_t = 250)]
+        move_delay: u64,
+
+        /// Milliseconds to hold the final position
+        #[arg(long, default_value_t = 2000)]
+        end_delay: u64,
+    },
+
+    /// Draw a board, or every position of a recorded game, as SVG
+    Render {
+        /// Position to draw; see play --help for the notation
+        #[arg(long, required_unless_present = "replay", conflicts_with = "replay")]
+        board: Option<Position>,
+
+        /// Recording to draw, as a contact sheet (or numbered frames with --frames)
+        #[arg(long)]
+        replay: Option<String>,
+
+        /// File to write; with --frames, game.svg becomes game-00.svg, game-01.svg, ...
+        #[arg(short, long)]
+        out: String,
+
+        /// Draw an arrow for this move (up, down, left or right) over the board
+        #[arg(long, conflicts_with = "replay")]
+        arrow: Option<Move>,
+
+        /// Write one file per position of the replay instead of a single contact sheet
+        #[arg(long, conflicts_with = "board")]
+        frames: bool,
+
+        /// Boards per row on a contact sheet
+        #[arg(long, default_value_t = 4)]
+        columns: usize,
+    },
+
+    /// Print high scores and stats from games played by hand, for every board size and spawn
+    /// policy (or just the one given by --size)
+    Stats {
+        /// Stats file to read; defaults to $XDG_DATA_HOME/ai2048/stats.json
+        #[arg(long)]
+        stats_file: Option<String>,
+    },
+
+    /// Play games over stdin/stdout with line-delimited JSON, for agents written elsewhere.
+    /// Requests: new_game (size, seed, spawn_policy), state, legal_moves, move
+    Engine,
+
+    /// Host many games at once over TCP on localhost, one line of JSON per request. Requests:
+    /// create, state, move, undo, close, list (by game id), and policy if --model is given
+    Serve {
+        /// Port to listen on
+        #[arg(short, long, default_value_t = 2048)]
+        port: u16,
+
+        /// Model checkpoint for the policy request, which judges any board its size
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+
+    /// Watch several agents play side by side on the same spawn sequence
+    Compare {
+        /// Seed shared by every board; random if not given
+        #[arg(short, long)]
+        seed: Option<u64>,
+
+        /// How new pieces spawn: even (50% 2s) or classic (90% 2s)
+        #[arg(long, default_value = "even")]
+        spawn: SpawnPolicy,
+
+        /// Agents to compare (give at least two): untrained, model:<path>, expectimax[:<depth>],
+        /// mc[:<options>], mcts[:<options>], or external:<command>
+        #[arg(short, long = "agent", required = true)]
+        agents: Vec<AgentSpec>,
     },
 
     /// Indicate we want to train a new model
@@ -55,7 +261,98 @@ pub enum Commands {
         /// L2 regularization amount
 
         /// Discount factor for reinforcement learning
-        #[arg(short, long, default_value_t = 0.0001)]
+        #[arg(long, default_value_t = 0.0001)]
         l2_reg: f32,
+
+        #[command(flatten)]
+        reward: RewardArgs,
+
+        #[command(flatten)]
+        alphazero: AlphaZeroArgs,
+
+        /// Show a live dashboard instead of printing progress lines
+        #[arg(long)]
+        dashboard: bool,
     },
 }
+
+/// AlphaZero-style training, where self-play moves come from a tree search guided by the model
+#[derive(Args, Debug)]
+pub struct AlphaZeroArgs {
+    /// Pick self-play moves by MCTS and train the actor toward its visit counts, instead of REINFORCE
+    #[arg(long)]
+    pub alphazero: bool,
+
+    /// Search settings for self-play (see autoplay --help for the options; the model being
+    /// trained is used, so there's no model=<path>)
+    #[arg(long, default_value = "sims=50,leaf=critic,priors=true", requires = "alphazero")]
+    pub mcts: MctsConfig,
+
+    /// Train the critic toward n-step returns, bootstrapped from its own value n moves later,
+    /// instead of every point to the end of the game. The critic always learns the undiscounted
+    /// score, since that's how the search uses it; --discount-factor and the reward weights only
+    /// apply to REINFORCE
+    #[arg(long, requires = "alphazero")]
+    pub bootstrap_steps: Option<usize>,
+
+    /// Moves at the start of each game picked in proportion to the visit counts, for exploration
+    #[arg(long, default_value_t = 30, requires = "alphazero")]
+    pub sample_moves: usize,
+}
+
+impl From<AlphaZeroArgs> for TrainingMode {
+    fn from(args: AlphaZeroArgs) -> Self {
+        if !args.alphazero {
+            return TrainingMode::Reinforce;
+        }
+        TrainingMode::AlphaZero(AlphaZeroConfig {
+            mcts: args.mcts,
+            bootstrap_steps: args.bootstrap_steps,
+            sample_moves: args.sample_moves,
+        })
+    }
+}
+
+/// Weights for the reward shaping terms; the defaults reproduce the plain score delta
+#[derive(Args, Debug)]
+pub struct RewardArgs {
+    /// Weight for the score delta of each move
+    #[arg(long, default_value_t = 1.0)]
+    pub reward_score_delta: f32,
+
+    /// Weight for log2(1 + score delta) of each move
+    #[arg(long, default_value_t = 0.0)]
+    pub reward_log_merge: f32,
+
+    /// Weight for the number of empty cells after each move
+    #[arg(long, default_value_t = 0.0)]
+    pub reward_empty_cells: f32,
+
+    /// Weight for a flat bonus on every move survived
+    #[arg(long, default_value_t = 0.0)]
+    pub reward_survival: f32,
+
+    /// Penalty applied to the move that ends the game
+    #[arg(long, default_value_t = 0.0)]
+    pub reward_game_over: f32,
+
+    /// Weight for log2 of each new highest tile reached
+    #[arg(long, default_value_t = 0.0)]
+    pub reward_max_tile: f32,
+}
+
+impl From<RewardArgs> for RewardWeights {
+    fn from(args: RewardArgs) -> Self {
+        RewardWeights {
+            score_delta: args.reward_score_delta,
+            log_merge: args.reward_log_merge,
+            empty_cells: args.reward_empty_cells,
+            survival: args.reward_survival,
+            game_over: args.reward_game_over,
+            max_tile: args.reward_max_tile,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests;
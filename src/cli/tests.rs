@@ -0,0 +1,10 @@
+use clap::CommandFactory;
+
+use super::Cli;
+
+/// Catches flags that clash with each other (e.g. two arguments claiming the same short flag),
+/// which clap only reports when that subcommand is parsed
+#[test]
+fn test_cli_definition() {
+    Cli::command().debug_assert();
+}
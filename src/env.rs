@@ -0,0 +1,413 @@
+//! A Gym-style environment around the game, so learning algorithms don't each need their own
+//! game loop. `reset` starts a new game and `step` plays one move, handing back an
+//! `Observation` (encoded board plus a mask of legal moves), the reward, and whether the episode
+//! ended. How boards are encoded and how moves are rewarded are both pluggable. `VecEnv` does
+//! the same for many boards at once, with observations stacked ready for a batched tensor.
+
+#[cfg(feature = "burn")]
+use burn::prelude::Backend;
+#[cfg(feature = "burn")]
+use burn::prelude::Bool;
+#[cfg(feature = "burn")]
+use burn::prelude::Tensor;
+#[cfg(feature = "burn")]
+use burn::prelude::TensorData;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+use crate::rewards::RewardFn;
+
+#[cfg(test)]
+mod tests;
+
+/// Exponents 0 (empty) through 17 (131072, the largest tile a 4x4 board can hold) each get a
+/// channel in the one-hot encoding. That's the fewest any board gets; see `one_hot_channels`.
+pub const NUM_POSSIBLE_STATES_PER_CELL: usize = 18;
+
+/// Channels per cell in the one-hot encoding of an NxN board: one for every exponent the board can
+/// hold, but never fewer than `NUM_POSSIBLE_STATES_PER_CELL`, so 4x4 and smaller boards keep the
+/// encoding (and the networks) they always had
+pub const fn one_hot_channels(n: usize) -> usize {
+    // every cell filled, the last by a spawned 4, can merge up to 2^(n*n + 1)
+    let needed = n * n + 2;
+    if needed > NUM_POSSIBLE_STATES_PER_CELL {
+        needed
+    } else {
+        NUM_POSSIBLE_STATES_PER_CELL
+    }
+}
+
+/// Turns a board into the flat feature vector a learner sees
+pub trait ObservationEncoder<const N: usize> {
+    /// Length of every encoded observation
+    fn num_features(&self) -> usize;
+
+    /// The features for `state`, `num_features` of them
+    fn encode(&self, state: &GameState<N>) -> Vec<f32>;
+}
+
+/// One channel per possible exponent for every cell, row by row; this is what `PolicyNet` takes
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OneHotEncoder;
+
+impl<const N: usize> ObservationEncoder<N> for OneHotEncoder {
+    fn num_features(&self) -> usize {
+        N * N * one_hot_channels(N)
+    }
+
+    fn encode(&self, state: &GameState<N>) -> Vec<f32> {
+        let mut features = Vec::with_capacity(N * N * one_hot_channels(N));
+        for y in 0..N {
+            for x in 0..N {
+                let actual_val = state.get_val(x, y);
+                features.extend((0..one_hot_channels(N) as u8).map(|val| if val == actual_val { 1.0 } else { 0.0 }));
+            }
+        }
+        features
+    }
+}
+
+/// Just the exponent of every cell, row by row (0 for empty, 11 for 2048)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ExponentEncoder;
+
+impl<const N: usize> ObservationEncoder<N> for ExponentEncoder {
+    fn num_features(&self) -> usize {
+        N * N
+    }
+
+    fn encode(&self, state: &GameState<N>) -> Vec<f32> {
+        (0..N).flat_map(|y| (0..N).map(move |x| state.get_val(x, y) as f32)).collect()
+    }
+}
+
+/// What a learner sees of the current position
+#[derive(Clone, Debug, PartialEq)]
+pub struct Observation {
+    /// The board, as the encoder encodes it
+    pub features: Vec<f32>,
+    /// Which moves are legal, indexed by `Move::to_idx`
+    pub action_mask: [bool; 4],
+}
+
+/// Everything about a step that isn't the observation or the reward
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StepInfo {
+    /// Score after the step
+    pub score: u32,
+    /// Points the step scored
+    pub score_delta: u32,
+    /// Value (not exponent) of the biggest tile after the step
+    pub highest_tile: u32,
+    /// Steps taken since the last reset, including this one
+    pub num_steps: usize,
+    /// The action was illegal, so nothing happened (and the reward is zero)
+    pub illegal_move: bool,
+}
+
+/// The result of one `Env::step`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The position after the step
+    pub observation: Observation,
+    /// What the reward function made of the step
+    pub reward: f32,
+    /// The game is over
+    pub terminated: bool,
+    /// The episode hit its step limit before the game was over
+    pub truncated: bool,
+    /// Everything else about the step
+    pub info: StepInfo,
+}
+
+/// One game at a time, played a step at a time, with boards encoded by `E` and moves rewarded by `R`
+pub struct Env<const N: usize, E: ObservationEncoder<N>, R: RewardFn<N>> {
+    encoder: E,
+    reward_fn: R,
+    spawn_policy: SpawnPolicy,
+    max_steps: Option<usize>,
+    episode: Episode<N>,
+}
+
+impl<const N: usize, E: ObservationEncoder<N>, R: RewardFn<N>> Env<N, E, R> {
+    /// A new environment, already holding a random game; call `reset` to choose the seed
+    pub fn new(encoder: E, reward_fn: R) -> Self {
+        Self {
+            encoder,
+            reward_fn,
+            spawn_policy: SpawnPolicy::default(),
+            max_steps: None,
+            episode: Episode::start(rand::random(), SpawnPolicy::default()),
+        }
+    }
+
+    /// Spawn policy for games started by `reset`
+    pub fn with_spawn_policy(mut self, spawn_policy: SpawnPolicy) -> Self {
+        self.spawn_policy = spawn_policy;
+        self
+    }
+
+    /// Truncate episodes after this many steps
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Start a new game from the given seed, or a random one
+    pub fn reset(&mut self, seed: Option<u64>) -> Observation {
+        self.episode = Episode::start(seed.unwrap_or_else(rand::random), self.spawn_policy);
+        self.observe()
+    }
+
+    /// Play a move. Illegal moves leave the board alone and count as a step with no reward.
+    /// Panics if the game is already over; `reset` first.
+    pub fn step(&mut self, action: Move) -> Step {
+        let (reward, terminated, truncated, info) = self.episode.step(action, &self.reward_fn, self.max_steps);
+        Step {
+            observation: self.observe(),
+            reward,
+            terminated,
+            truncated,
+            info,
+        }
+    }
+
+    /// The current position, without stepping
+    pub fn observe(&self) -> Observation {
+        Observation {
+            features: self.encoder.encode(&self.episode.game),
+            action_mask: self.action_mask(),
+        }
+    }
+
+    /// Which moves are legal now, indexed by `Move::to_idx`
+    pub fn action_mask(&self) -> [bool; 4] {
+        self.episode.action_mask()
+    }
+
+    /// Length of every observation's features
+    pub fn num_features(&self) -> usize {
+        self.encoder.num_features()
+    }
+
+    /// The game being played
+    pub fn state(&self) -> &GameState<N> {
+        &self.episode.game
+    }
+}
+
+/// Observations of every board in a `VecEnv`, stacked so they can go straight into a tensor
+#[derive(Clone, Debug, PartialEq)]
+pub struct VecObservation {
+    /// One row of `num_features` per board, so `[K, F]` in row-major order
+    pub features: Vec<f32>,
+    /// Length of each board's row
+    pub num_features: usize,
+    /// Which moves are legal on each board, indexed by `Move::to_idx`
+    pub action_masks: Vec<[bool; 4]>,
+}
+
+impl VecObservation {
+    /// Number of boards
+    pub fn num_envs(&self) -> usize {
+        self.action_masks.len()
+    }
+
+    /// Features of a single board
+    pub fn row(&self, i: usize) -> &[f32] {
+        &self.features[i * self.num_features..(i + 1) * self.num_features]
+    }
+
+    /// The features as a `[K, F]` tensor
+    #[cfg(feature = "burn")]
+    pub fn features_tensor<B: Backend>(&self, device: &B::Device) -> Tensor<B, 2> {
+        Tensor::from_data(TensorData::new(self.features.clone(), [self.num_envs(), self.num_features]), device)
+    }
+
+    /// The action masks as a `[K, 4]` tensor
+    #[cfg(feature = "burn")]
+    pub fn mask_tensor<B: Backend>(&self, device: &B::Device) -> Tensor<B, 2, Bool> {
+        let mask: Vec<bool> = self.action_masks.iter().flatten().copied().collect();
+        Tensor::from_data(TensorData::new(mask, [self.num_envs(), 4]), device)
+    }
+}
+
+/// The result of stepping every board in a `VecEnv`. Boards whose episode ended were reset
+/// straight away: their observation is the new game, while the reward, flags and info describe
+/// the step that ended the old one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VecStep {
+    /// Every board after the step; see above for boards that were reset
+    pub observation: VecObservation,
+    /// Each board's reward
+    pub rewards: Vec<f32>,
+    /// Which boards' games ended
+    pub terminated: Vec<bool>,
+    /// Which boards hit the step limit
+    pub truncated: Vec<bool>,
+    /// Everything else about each board's step
+    pub infos: Vec<StepInfo>,
+}
+
+/// K independent games stepped together, for batched self-play. Each board has its own RNG;
+/// their seeds all come from one seed, so a seeded run is reproducible, auto-resets included.
+pub struct VecEnv<const N: usize, E: ObservationEncoder<N>, R: RewardFn<N>> {
+    encoder: E,
+    reward_fn: R,
+    spawn_policy: SpawnPolicy,
+    max_steps: Option<usize>,
+    seeds: StdRng,
+    boards: Vec<Episode<N>>,
+}
+
+impl<const N: usize, E: ObservationEncoder<N>, R: RewardFn<N>> VecEnv<N, E, R> {
+    /// `num_envs` boards, already holding random games; call `reset` to choose the seed
+    pub fn new(num_envs: usize, encoder: E, reward_fn: R) -> Self {
+        let mut out = Self {
+            encoder,
+            reward_fn,
+            spawn_policy: SpawnPolicy::default(),
+            max_steps: None,
+            seeds: StdRng::from_os_rng(),
+            boards: Vec::with_capacity(num_envs),
+        };
+        out.boards = (0..num_envs).map(|_| out.next_episode()).collect();
+        out
+    }
+
+    /// Spawn policy for games started by `reset` and auto-resets
+    pub fn with_spawn_policy(mut self, spawn_policy: SpawnPolicy) -> Self {
+        self.spawn_policy = spawn_policy;
+        self
+    }
+
+    /// Truncate episodes after this many steps
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Start every board over, seeding them all from `seed` (or a random one)
+    pub fn reset(&mut self, seed: Option<u64>) -> VecObservation {
+        self.seeds = StdRng::seed_from_u64(seed.unwrap_or_else(rand::random));
+        for i in 0..self.boards.len() {
+            self.boards[i] = self.next_episode();
+        }
+        self.observe()
+    }
+
+    /// Play one move on every board; `actions[i]` goes to board i. Finished boards are reset.
+    pub fn step(&mut self, actions: &[Move]) -> VecStep {
+        assert_eq!(actions.len(), self.boards.len(), "Need exactly one action per board");
+
+        let k = self.boards.len();
+        let (mut rewards, mut terminated, mut truncated, mut infos) = (
+            Vec::with_capacity(k),
+            Vec::with_capacity(k),
+            Vec::with_capacity(k),
+            Vec::with_capacity(k),
+        );
+
+        for (i, &action) in actions.iter().enumerate() {
+            let (reward, done, cut_off, info) = self.boards[i].step(action, &self.reward_fn, self.max_steps);
+            if done || cut_off {
+                self.boards[i] = self.next_episode();
+            }
+            rewards.push(reward);
+            terminated.push(done);
+            truncated.push(cut_off);
+            infos.push(info);
+        }
+
+        VecStep {
+            observation: self.observe(),
+            rewards,
+            terminated,
+            truncated,
+            infos,
+        }
+    }
+
+    /// Every board's current position, without stepping
+    pub fn observe(&self) -> VecObservation {
+        let mut features = Vec::with_capacity(self.boards.len() * self.num_features());
+        for board in &self.boards {
+            features.extend(self.encoder.encode(&board.game));
+        }
+        VecObservation {
+            features,
+            num_features: self.num_features(),
+            action_masks: self.boards.iter().map(Episode::action_mask).collect(),
+        }
+    }
+
+    /// Number of boards
+    pub fn num_envs(&self) -> usize {
+        self.boards.len()
+    }
+
+    /// Length of each board's features
+    pub fn num_features(&self) -> usize {
+        self.encoder.num_features()
+    }
+
+    /// The game being played on board `i`
+    pub fn state(&self, i: usize) -> &GameState<N> {
+        &self.boards[i].game
+    }
+
+    fn next_episode(&mut self) -> Episode<N> {
+        Episode::start(self.seeds.random(), self.spawn_policy)
+    }
+}
+
+/// A single game in progress, and how many steps it's been going
+struct Episode<const N: usize> {
+    game: GameState<N>,
+    rng: RngPlacement,
+    num_steps: usize,
+}
+
+impl<const N: usize> Episode<N> {
+    fn start(seed: u64, spawn_policy: SpawnPolicy) -> Self {
+        let mut rng = RngPlacement::new_from_seed(seed).with_spawn_policy(spawn_policy);
+        let game = GameState::new_random(&mut rng);
+        Self { game, rng, num_steps: 0 }
+    }
+
+    /// Play a move, returning the reward, whether the game ended or was cut off, and the info
+    fn step<R: RewardFn<N>>(&mut self, action: Move, reward_fn: &R, max_steps: Option<usize>) -> (f32, bool, bool, StepInfo) {
+        assert!(!self.game.is_finished(), "The game is over; call reset to start another");
+
+        let before = self.game;
+        let (reward, illegal_move) = match before.apply_move(action, &mut self.rng) {
+            Ok(after) => {
+                self.game = after;
+                (reward_fn.reward(&before, action, &after), false)
+            }
+            Err(_) => (0.0, true),
+        };
+        self.num_steps += 1;
+
+        let terminated = self.game.is_finished();
+        let truncated = !terminated && max_steps.is_some_and(|max| self.num_steps >= max);
+        let info = StepInfo {
+            score: self.game.current_score(),
+            score_delta: self.game.current_score() - before.current_score(),
+            highest_tile: self.game.highest_tile(),
+            num_steps: self.num_steps,
+            illegal_move,
+        };
+        (reward, terminated, truncated, info)
+    }
+
+    fn action_mask(&self) -> [bool; 4] {
+        Move::ALL.map(|m| self.game.is_legal_move(m))
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(feature = "burn")]
+use burn::backend::NdArray;
+#[cfg(feature = "burn")]
+use burn::backend::ndarray::NdArrayDevice;
+
+use crate::env::Env;
+use crate::env::ExponentEncoder;
+use crate::env::NUM_POSSIBLE_STATES_PER_CELL;
+use crate::env::ObservationEncoder;
+use crate::env::OneHotEncoder;
+use crate::env::VecEnv;
+use crate::env::one_hot_channels;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_traits::FullGame;
+use crate::rewards::ScoreDelta;
+use crate::rewards::SurvivalBonus;
+
+#[test]
+fn test_encoders() {
+    #[rustfmt::skip]
+    let state = GameState::from_grid(
+        [
+            [1, 0, 0],
+            [0, 11, 0],
+            [0, 0, 17],
+        ],
+        0,
+    );
+
+    let exponents = ExponentEncoder.encode(&state);
+    assert_eq!(exponents, vec![1.0, 0.0, 0.0, 0.0, 11.0, 0.0, 0.0, 0.0, 17.0]);
+
+    let one_hot = OneHotEncoder.encode(&state);
+    assert_eq!(one_hot.len(), ObservationEncoder::<3>::num_features(&OneHotEncoder));
+    assert_eq!(one_hot.iter().sum::<f32>(), 9.0);
+    let hot: Vec<usize> = (0..one_hot.len())
+        .filter(|&i| one_hot[i] == 1.0)
+        .map(|i| i % NUM_POSSIBLE_STATES_PER_CELL)
+        .collect();
+    assert_eq!(hot, vec![1, 0, 0, 0, 11, 0, 0, 0, 17]);
+}
+
+#[test]
+fn test_one_hot_fits_the_largest_tile() {
+    assert_eq!(one_hot_channels(3), NUM_POSSIBLE_STATES_PER_CELL);
+    assert_eq!(one_hot_channels(4), NUM_POSSIBLE_STATES_PER_CELL);
+    assert_eq!(one_hot_channels(5), 27);
+
+    // 2^26, the largest tile a 5x5 board can hold
+    let state = GameState::<5>::new_empty().with_tile(4, 0, 26).with_tile(0, 1, 18);
+    let one_hot = OneHotEncoder.encode(&state);
+    assert_eq!(one_hot.len(), ObservationEncoder::<5>::num_features(&OneHotEncoder));
+    let hot: Vec<usize> = (0..one_hot.len()).filter(|&i| one_hot[i] == 1.0).collect();
+    assert_eq!(hot.len(), 25);
+    assert!(hot.contains(&(4 * 27 + 26)));
+    assert!(hot.contains(&(5 * 27 + 18)));
+}
+
+#[test]
+fn test_reset_and_step_follow_the_seed() {
+    let mut env: Env<4, _, _> = Env::new(ExponentEncoder, ScoreDelta);
+    let first = env.reset(Some(3));
+
+    // the same game, played by hand
+    let mut rng = RngPlacement::new_from_seed(3);
+    let mut game = GameState::<4>::new_random(&mut rng);
+    assert_eq!(first.features, ExponentEncoder.encode(&game));
+
+    for i in 0..30 {
+        let mask = env.action_mask();
+        assert_eq!(mask, Move::ALL.map(|m| game.is_legal_move(m)));
+
+        let m = Move::ALL.into_iter().cycle().skip(i).find(|&m| mask[m.to_idx()]).unwrap();
+        let step = env.step(m);
+        let next = game.apply_move(m, &mut rng).unwrap();
+
+        assert_eq!(step.observation.features, ExponentEncoder.encode(&next));
+        assert_eq!(step.reward, (next.current_score() - game.current_score()) as f32);
+        assert_eq!(step.info.score, next.current_score());
+        assert_eq!(step.info.num_steps, i + 1);
+        assert!(!step.truncated);
+        game = next;
+        if step.terminated {
+            break;
+        }
+    }
+
+    // resetting with the same seed starts over exactly
+    assert_eq!(env.reset(Some(3)), first);
+}
+
+#[test]
+fn test_illegal_moves_and_truncation() {
+    let mut env: Env<4, _, _> = Env::new(OneHotEncoder, SurvivalBonus).with_max_steps(2);
+    let start = env.reset(Some(0));
+    let illegal = Move::ALL.into_iter().find(|m| !start.action_mask[m.to_idx()]).unwrap();
+    let legal = Move::ALL.into_iter().find(|m| start.action_mask[m.to_idx()]).unwrap();
+
+    let step = env.step(illegal);
+    assert!(step.info.illegal_move);
+    assert_eq!(step.observation, start);
+    assert_eq!(step.reward, 0.0);
+    assert!(!step.truncated);
+
+    let step = env.step(legal);
+    assert!(!step.info.illegal_move);
+    assert_eq!(step.reward, 1.0);
+    assert!(step.truncated);
+    assert!(!step.terminated);
+}
+
+/// First legal move on every board
+fn first_legal(masks: &[[bool; 4]]) -> Vec<Move> {
+    masks
+        .iter()
+        .map(|mask| Move::ALL.into_iter().find(|m| mask[m.to_idx()]).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_vec_env_stacks_boards() {
+    let mut envs: VecEnv<4, _, _> = VecEnv::new(5, OneHotEncoder, ScoreDelta);
+    let obs = envs.reset(Some(11));
+    assert_eq!(obs.num_envs(), 5);
+    assert_eq!(obs.features.len(), 5 * obs.num_features);
+    for i in 0..5 {
+        assert_eq!(obs.row(i), OneHotEncoder.encode(envs.state(i)));
+        assert_eq!(obs.action_masks[i], Move::ALL.map(|m| envs.state(i).is_legal_move(m)));
+    }
+
+    // each board has its own RNG, so they don't all start the same
+    assert!((1..5).any(|i| envs.state(i) != envs.state(0)));
+
+    // and a seeded run is repeatable
+    let mut again: VecEnv<4, _, _> = VecEnv::new(5, OneHotEncoder, ScoreDelta);
+    assert_eq!(again.reset(Some(11)), obs);
+    let actions = first_legal(&obs.action_masks);
+    assert_eq!(again.step(&actions), envs.step(&actions));
+}
+
+#[test]
+fn test_vec_env_auto_resets() {
+    // 3x3 games using the first legal move end quickly
+    let mut envs: VecEnv<3, _, _> = VecEnv::new(4, ExponentEncoder, ScoreDelta).with_max_steps(1000);
+    let mut obs = envs.reset(Some(2));
+    let mut finished = 0;
+
+    for _ in 0..200 {
+        let step = envs.step(&first_legal(&obs.action_masks));
+        for i in 0..4 {
+            if step.terminated[i] {
+                finished += 1;
+                // the board was replaced by a new game with a single tile
+                assert_eq!(step.observation.row(i).iter().filter(|&&val| val > 0.0).count(), 1);
+                assert!(step.infos[i].score > 0);
+            }
+        }
+        obs = step.observation;
+    }
+
+    assert!(finished > 0);
+}
+
+#[cfg(feature = "burn")]
+#[test]
+fn test_vec_observation_tensors() {
+    let mut envs: VecEnv<4, _, _> = VecEnv::new(3, OneHotEncoder, ScoreDelta);
+    let obs = envs.reset(Some(0));
+    let device = NdArrayDevice::default();
+
+    let features = obs.features_tensor::<NdArray>(&device);
+    assert_eq!(features.dims(), [3, 4 * 4 * NUM_POSSIBLE_STATES_PER_CELL]);
+    assert_eq!(features.into_data().into_vec::<f32>().unwrap(), obs.features);
+
+    let masks = obs.mask_tensor::<NdArray>(&device);
+    assert_eq!(masks.dims(), [3, 4]);
+    let flat: Vec<bool> = obs.action_masks.iter().flatten().copied().collect();
+    assert_eq!(masks.into_data().into_vec::<bool>().unwrap(), flat);
+}
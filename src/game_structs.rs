@@ -1,8 +1,17 @@
+//! The board itself: [`GameState`] and its moves, plus [`RngPlacement`], which places new
+//! pieces from a seed so games can be replayed exactly.
+
 // This really is what I want, clippy, get off my back
 #![allow(clippy::needless_range_loop)]
 
+use std::fmt;
+use std::str::FromStr;
+
 use rand::Rng;
 use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::Deserialize;
+use serde::Serialize;
 
 use crate::game_traits::AddRandomPiece;
 use crate::game_traits::FullGame;
@@ -10,6 +19,7 @@ use crate::game_traits::FullGame;
 #[cfg(test)]
 mod tests;
 
+/// An NxN board and its score. Tiles are stored as exponents: 0 for empty, n for a 2^n tile.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct GameState<const N: usize> {
     // 0 means empty square; n>0 means 2<<n
@@ -19,15 +29,25 @@ pub struct GameState<const N: usize> {
     current_score: u32,
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+/// A slide of every tile in one direction
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Move {
+    /// Toward the top row
     Up,
+    /// Toward the bottom row
     Down,
+    /// Toward the left column
     Left,
+    /// Toward the right column
     Right,
 }
 
 impl Move {
+    /// Every move, in index order (see `to_idx`)
+    pub const ALL: [Move; 4] = [Move::Up, Move::Down, Move::Left, Move::Right];
+
+    /// Position in `ALL`, for indexing per-move arrays
     pub fn to_idx(self) -> usize {
         match self {
             Move::Up => 0,
@@ -37,6 +57,7 @@ impl Move {
         }
     }
 
+    /// Inverse of `to_idx`; anything past 3 is `Right`
     pub fn from_idx(idx: usize) -> Move {
         match idx {
             0 => Move::Up,
@@ -47,13 +68,30 @@ impl Move {
     }
 }
 
+impl FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "up" => Ok(Move::Up),
+            "down" => Ok(Move::Down),
+            "left" => Ok(Move::Left),
+            "right" => Ok(Move::Right),
+            _ => Err(format!("Unknown move '{s}'; expected up, down, left or right")),
+        }
+    }
+}
+
+/// Why a move couldn't be made
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum MoveError {
+    /// The move wouldn't change the board
     IllegalMove,
     // other variants?
 }
 
 impl<const N: usize> GameState<N> {
+    /// A board with no tiles and no score
     pub fn new_empty() -> Self {
         Self {
             grid: [[0; N]; N],
@@ -61,16 +99,85 @@ impl<const N: usize> GameState<N> {
         }
     }
 
+    /// Builds a state directly from the exponent grid (indexed `[y][x]`, 0 for empty, n for 2^n)
+    #[cfg(test)]
+    pub fn from_grid(grid: [[u8; N]; N], current_score: u32) -> Self {
+        Self { grid, current_score }
+    }
+
+    /// A board with a single piece placed by `r`, which is how games start
     pub fn new_random<R: AddRandomPiece<Self>>(r: &mut R) -> Self {
         let out = Self::new_empty();
         r.next_piece(&out)
     }
 
+    /// Exponent of the tile in column `x`, row `y` (0 for empty)
     #[inline(always)]
     pub fn get_val(&self, x: usize, y: usize) -> u8 {
         self.grid[y][x]
     }
 
+    /// The board as actual tile values (0 for empty), row by row from the top
+    pub fn tile_values(&self) -> Vec<Vec<u32>> {
+        self.grid
+            .iter()
+            .map(|row| row.iter().map(|&val| if val == 0 { 0 } else { 1 << val }).collect())
+            .collect()
+    }
+
+    /// Inverse of `tile_values`: every value must be 0 or a power of two (at least 2)
+    pub fn from_tile_values(rows: &[Vec<u32>], current_score: u32) -> Result<Self, String> {
+        if rows.len() != N || rows.iter().any(|row| row.len() != N) {
+            return Err(format!("Expected a {N}x{N} board"));
+        }
+
+        let mut out = Self::new_empty();
+        out.current_score = current_score;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, &val) in row.iter().enumerate() {
+                out.grid[y][x] = match val {
+                    0 => 0,
+                    v if v >= 2 && v.is_power_of_two() => v.trailing_zeros() as u8,
+                    v => return Err(format!("{v} is not a valid tile")),
+                };
+            }
+        }
+
+        Ok(out)
+    }
+
+    /// Copy of this state with the tile at (x, y) set to `val` (same encoding as `get_val`)
+    pub fn with_tile(&self, x: usize, y: usize, val: u8) -> Self {
+        let mut out = *self;
+        out.grid[y][x] = val;
+        out
+    }
+
+    /// Coordinates (x, y) of every empty square
+    pub fn empty_cells(&self) -> Vec<(usize, usize)> {
+        let mut out = Vec::new();
+        for x in 0..N {
+            for y in 0..N {
+                if self.grid[y][x] == 0 {
+                    out.push((x, y));
+                }
+            }
+        }
+        out
+    }
+
+    /// The "afterstate": slide everything in the direction of the move, but don't add a new
+    /// piece. If the move is illegal, this is just a copy of `self`.
+    pub fn slide(&self, m: Move) -> Self {
+        match m {
+            Move::Up => self.up(),
+            Move::Down => self.down(),
+            Move::Left => self.left(),
+            Move::Right => self.right(),
+        }
+    }
+
     fn left(&self) -> Self {
         let mut out = *self;
 
@@ -272,6 +379,7 @@ impl<const N: usize> GameState<N> {
     }
 
     // TODO: unit test
+    /// Value (not exponent) of the biggest tile, or 0 on an empty board
     pub fn highest_tile(&self) -> u32 {
         self.grid
             .iter()
@@ -285,14 +393,7 @@ impl<const N: usize> GameState<N> {
 
 impl<const N: usize> FullGame for GameState<N> {
     fn apply_move<R: AddRandomPiece<GameState<N>>>(&self, m: Move, r: &mut R) -> Result<GameState<N>, MoveError> {
-        let mut next_state = *self;
-
-        next_state = match m {
-            Move::Up => next_state.up(),
-            Move::Down => next_state.down(),
-            Move::Left => next_state.left(),
-            Move::Right => next_state.right(),
-        };
+        let mut next_state = self.slide(m);
 
         if &next_state == self {
             return Err(MoveError::IllegalMove);
@@ -305,17 +406,12 @@ impl<const N: usize> FullGame for GameState<N> {
 
     // TODO: unit test
     fn is_legal_move(&self, m: Move) -> bool {
-        self != &match m {
-            Move::Left => self.left(),
-            Move::Right => self.right(),
-            Move::Up => self.up(),
-            Move::Down => self.down(),
-        }
+        self != &self.slide(m)
     }
 
     // TODO: unit test (including nearly-empty grid, full grid that is not locked, and full grid that is locked)
     fn is_finished(&self) -> bool {
-        for m in [Move::Left, Move::Right, Move::Up, Move::Down] {
+        for m in Move::ALL {
             if self.is_legal_move(m) {
                 return false;
             }
@@ -328,38 +424,125 @@ impl<const N: usize> FullGame for GameState<N> {
     }
 }
 
+/// How new pieces are chosen: a 2 with some probability, otherwise a 4
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpawnPolicy {
+    /// 2 and 4 equally likely; what this project has always used
+    #[default]
+    Even,
+    /// 90% 2s and 10% 4s, like the original game
+    Classic,
+}
+
+impl SpawnPolicy {
+    /// Chance that a newly placed piece is a 2 (otherwise it's a 4)
+    pub const fn two_probability(self) -> f64 {
+        match self {
+            SpawnPolicy::Even => 0.5,
+            SpawnPolicy::Classic => 0.9,
+        }
+    }
+}
+
+impl FromStr for SpawnPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "even" => Ok(SpawnPolicy::Even),
+            "classic" => Ok(SpawnPolicy::Classic),
+            other => Err(format!("Unknown spawn policy '{other}'; expected even or classic")),
+        }
+    }
+}
+
+impl fmt::Display for SpawnPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnPolicy::Even => write!(f, "even"),
+            SpawnPolicy::Classic => write!(f, "classic"),
+        }
+    }
+}
+
+/// Chance that a newly placed piece is a 2 under the default spawn policy
+pub const SPAWN_TWO_PROBABILITY: f64 = SpawnPolicy::Even.two_probability();
+
+/// Everything needed to pick up an `RngPlacement` exactly where it left off
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RngState {
+    /// Seed the generator was created with
+    pub seed: u64,
+    /// How many 32-bit words have been drawn from the generator so far
+    pub word_pos: u128,
+    /// How pieces are chosen
+    pub spawn_policy: SpawnPolicy,
+}
+
+/// Places new pieces at random, from a seeded generator whose position can be saved and
+/// restored (see `RngState`)
 pub struct RngPlacement {
-    rng: rand::rngs::StdRng,
+    // ChaCha12 is what StdRng uses, so seeded games are unchanged, but unlike StdRng it can
+    // report and restore its position in the stream
+    rng: ChaCha12Rng,
+    seed: u64,
+    spawn_policy: SpawnPolicy,
+}
+
+impl Default for RngPlacement {
+    /// Same as `new`: a random seed
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RngPlacement {
+    /// A generator with a random seed
     pub fn new() -> RngPlacement {
         let seed: u64 = rand::random();
         Self::new_from_seed(seed)
     }
 
+    /// A generator with a fixed seed; the same seed always places the same pieces
     pub fn new_from_seed(seed: u64) -> RngPlacement {
         RngPlacement {
-            rng: rand::rngs::StdRng::seed_from_u64(seed),
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            seed,
+            spawn_policy: SpawnPolicy::default(),
         }
     }
+
+    /// Choose pieces with `spawn_policy` instead of the default
+    pub fn with_spawn_policy(self, spawn_policy: SpawnPolicy) -> RngPlacement {
+        RngPlacement { spawn_policy, ..self }
+    }
+
+    /// Where the generator is now, to be picked up again with `from_state`
+    pub fn state(&self) -> RngState {
+        RngState {
+            seed: self.seed,
+            word_pos: self.rng.get_word_pos(),
+            spawn_policy: self.spawn_policy,
+        }
+    }
+
+    /// Continue from a saved state; the pieces placed from here on are the same ones the
+    /// original would have placed
+    pub fn from_state(state: RngState) -> RngPlacement {
+        let mut out = Self::new_from_seed(state.seed).with_spawn_policy(state.spawn_policy);
+        out.rng.set_word_pos(state.word_pos);
+        out
+    }
 }
 
 impl<const N: usize> AddRandomPiece<GameState<N>> for RngPlacement {
     fn next_piece(&mut self, in_state: &GameState<N>) -> GameState<N> {
-        let mut free_spaces = Vec::new();
-
-        for x in 0..N {
-            for y in 0..N {
-                if in_state.grid[y][x] == 0 {
-                    free_spaces.push((x, y));
-                }
-            }
-        }
+        let free_spaces = in_state.empty_cells();
 
         let (x, y) = free_spaces[self.rng.random_range(0..free_spaces.len())];
 
-        let is_two = self.rng.random_bool(0.5);
+        let is_two = self.rng.random_bool(self.spawn_policy.two_probability());
 
         let mut out_state = *in_state;
         out_state.grid[y][x] = if is_two { 1 } else { 2 };
@@ -4,6 +4,7 @@
 use crate::game_structs::GameState;
 use crate::game_structs::Move;
 use crate::game_structs::RngPlacement;
+use crate::game_structs::SpawnPolicy;
 use crate::game_traits::AddRandomPiece;
 use crate::game_traits::FullGame;
 
@@ -663,3 +664,101 @@ fn test_prng_pieces_on_new_places_only() {
         assert_eq!(new_count, 1);
     }
 }
+
+#[test]
+fn test_slide_does_not_place_pieces() {
+    let start = crowded_grid_a();
+
+    for m in Move::ALL {
+        let mut rng = NoPlacement {};
+        let expected = start.apply_move(m, &mut rng).unwrap_or(start);
+        assert_eq!(start.slide(m), expected, "slide {m:?} should match a move with no placement");
+    }
+}
+
+#[test]
+fn test_empty_cells_and_with_tile() {
+    let start = boring_grid();
+
+    let empty = start.empty_cells();
+    assert_eq!(empty.len(), 24);
+    assert!(!empty.contains(&(2, 2)), "the occupied square is not empty");
+
+    let placed = start.with_tile(4, 0, 3);
+    assert_eq!(placed.get_val(4, 0), 3);
+    assert_eq!(placed.empty_cells().len(), 23);
+    assert_eq!(placed.current_score(), start.current_score(), "placing a tile does not score");
+}
+
+#[test]
+fn test_spawn_policy_frequencies() {
+    fn fraction_of_twos(policy: SpawnPolicy) -> f64 {
+        let mut rng = RngPlacement::new_from_seed(7).with_spawn_policy(policy);
+        let trials = 4000;
+        let twos = (0..trials)
+            .filter(|_| {
+                let state: GameState<4> = rng.next_piece(&GameState::new_empty());
+                state.highest_tile() == 2
+            })
+            .count();
+        twos as f64 / trials as f64
+    }
+
+    assert!((fraction_of_twos(SpawnPolicy::Even) - 0.5).abs() < 0.05);
+    assert!((fraction_of_twos(SpawnPolicy::Classic) - 0.9).abs() < 0.05);
+}
+
+#[test]
+fn test_spawn_policy_parsing() {
+    for policy in [SpawnPolicy::Even, SpawnPolicy::Classic] {
+        assert_eq!(policy.to_string().parse(), Ok(policy));
+    }
+    assert!("uniform".parse::<SpawnPolicy>().is_err());
+}
+
+#[test]
+fn test_move_parsing() {
+    assert_eq!("up".parse(), Ok(Move::Up));
+    assert_eq!("Left".parse(), Ok(Move::Left));
+    assert!("sideways".parse::<Move>().is_err());
+}
+
+#[test]
+fn test_rng_matches_std_rng() {
+    // games recorded before the switch to ChaCha12Rng must replay identically from their seeds
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    let mut std_rng = rand::rngs::StdRng::seed_from_u64(99);
+    let mut placement = RngPlacement::new_from_seed(99);
+    let mut state = GameState::<4>::new_empty();
+    for _ in 0..10 {
+        let free = state.empty_cells();
+        let (x, y) = free[std_rng.random_range(0..free.len())];
+        let val = if std_rng.random_bool(0.5) { 1 } else { 2 };
+
+        let next = placement.next_piece(&state);
+        assert_eq!(next, state.with_tile(x, y, val));
+        state = next;
+    }
+}
+
+#[test]
+fn test_rng_state_restores_upcoming_pieces() {
+    let mut rng = RngPlacement::new_from_seed(1234).with_spawn_policy(SpawnPolicy::Classic);
+    let mut state = GameState::<4>::new_random(&mut rng);
+    for _ in 0..5 {
+        state = rng.next_piece(&state);
+    }
+
+    let saved = rng.state();
+    assert_eq!(saved.seed, 1234);
+    assert_eq!(saved.spawn_policy, SpawnPolicy::Classic);
+
+    let mut restored = RngPlacement::from_state(saved);
+    for _ in 0..5 {
+        let expected = rng.next_piece(&state);
+        assert_eq!(restored.next_piece(&state), expected);
+        state = expected;
+    }
+}
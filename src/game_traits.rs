@@ -1,6 +1,9 @@
+//! The rules of the game, apart from any one board representation
+
 use crate::game_structs::Move;
 use crate::game_structs::MoveError;
 
+/// A game that can be played move by move
 pub trait FullGame: Sized {
     /// Apply the move, then use an appropriate RNG to add the next square
     /// Returns an error if the move is invalid (results in no moves)
@@ -16,6 +19,7 @@ pub trait FullGame: Sized {
     fn current_score(&self) -> u32;
 }
 
+/// Whatever places a new piece after each move: seeded, random, or scripted in tests
 pub trait AddRandomPiece<State> {
     /// Functionality for adding the next piece to the game state. Left intentionally very
     /// vague to support testability and, frankly, to make the type definitions simpler.
@@ -0,0 +1,122 @@
+//! A 2048 engine, and agents that play it.
+//!
+//! The engine itself ([`game_structs`], [`game_traits`]) is plain Rust: boards are
+//! `GameState<N>` for an NxN game, and new pieces come from an [`game_structs::RngPlacement`]
+//! so games can be replayed exactly. On top of that are saved sessions, replays, a text notation
+//! for positions, a JSON-lines protocol for driving games from other programs, a Gym-style
+//! [`env::Env`] for reinforcement learning, and a handful of [`agents::Agent`]s to play them.
+//!
+//! ```
+//! use ai2048::agents::Agent;
+//! use ai2048::agents::ExpectimaxAgent;
+//! use ai2048::game_structs::GameState;
+//! use ai2048::game_structs::RngPlacement;
+//! use ai2048::game_traits::FullGame;
+//!
+//! let mut rng = RngPlacement::new_from_seed(7);
+//! let mut game = GameState::<4>::new_random(&mut rng);
+//! let mut agent = ExpectimaxAgent::new(1);
+//!
+//! for _ in 0..20 {
+//!     let m = agent.choose_move(&game)?;
+//!     game = game.apply_move(m, &mut rng).expect("Agents only pick legal moves");
+//! }
+//! assert!(game.current_score() > 0);
+//! # Ok::<(), std::io::Error>(())
+//! ```
+//!
+//! # Features
+//!
+//! - `burn`: the neural network agent ([`model_structs`]), its training loop ([`training`]),
+//!   and the server's `policy` request. Without it, the crate doesn't depend on burn at all.
+//! - `wgpu`: burn's GPU backend, on top of `burn`.
+//! - `tui`: terminal play, autoplay, replays and comparisons ([`tui`]), plus the training
+//!   dashboard if `burn` is on too.
+//! - `cli`: everything the `ai2048` binary needs. This is the default.
+//! - `python`: a Python extension module with the engine and a vectorized environment; see
+//!   `python/test.sh` for building it.
+
+#![allow(clippy::let_and_return)]
+
+use std::ops::RangeInclusive;
+
+pub mod agents;
+pub mod analysis;
+pub mod env;
+pub mod game_structs;
+pub mod game_traits;
+
+#[cfg(feature = "burn")]
+pub mod model_structs;
+#[cfg(feature = "burn")]
+pub mod model_traits;
+
+pub mod notation;
+pub mod palette;
+pub mod protocol;
+#[cfg(feature = "python")]
+mod python;
+pub mod replay;
+pub mod rewards;
+pub mod server;
+pub mod session;
+pub mod stats;
+pub mod svg;
+#[cfg(feature = "burn")]
+pub mod training;
+
+#[cfg(feature = "tui")]
+pub mod tui;
+
+/// Board sizes that code taking the size at runtime is compiled for; see [`with_board_size!`]
+pub const SUPPORTED_SIZES: RangeInclusive<usize> = 3..=8;
+
+/// Board size used when nothing else picks one
+pub const DEFAULT_SIZE: usize = 4;
+
+/// Error text for a board size outside [`SUPPORTED_SIZES`]
+pub fn unsupported_size_message(size: usize) -> String {
+    format!(
+        "Unsupported board size {size}; supported sizes are {} to {}",
+        SUPPORTED_SIZES.start(),
+        SUPPORTED_SIZES.end()
+    )
+}
+
+/// Run `$body` with `$n` bound to `$size` as a const, for every board size in
+/// [`SUPPORTED_SIZES`]. `$body` must evaluate to an `io::Result`; other sizes are an error.
+#[macro_export]
+macro_rules! with_board_size {
+    ($size:expr, $n:ident => $body:expr) => {
+        match $size {
+            3 => {
+                const $n: usize = 3;
+                $body
+            }
+            4 => {
+                const $n: usize = 4;
+                $body
+            }
+            5 => {
+                const $n: usize = 5;
+                $body
+            }
+            6 => {
+                const $n: usize = 6;
+                $body
+            }
+            7 => {
+                const $n: usize = 7;
+                $body
+            }
+            8 => {
+                const $n: usize = 8;
+                $body
+            }
+            other => Err(::std::io::Error::new(
+                ::std::io::ErrorKind::InvalidInput,
+                $crate::unsupported_size_message(other),
+            )),
+        }
+    };
+}
@@ -1,7 +1,40 @@
-#![allow(clippy::let_and_return)]
-
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::BufWriter;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
+use ai2048::DEFAULT_SIZE;
+use ai2048::agents::Agent;
+use ai2048::agents::AgentSpec;
+use ai2048::agents::ModelAgent;
+use ai2048::analysis;
+use ai2048::analysis::AnalysisOptions;
+use ai2048::game_structs::Move;
+use ai2048::game_structs::SpawnPolicy;
+use ai2048::game_traits::FullGame;
+use ai2048::model_structs::PolicyNet;
+use ai2048::model_structs::PolicyNetConfig;
+use ai2048::model_structs::checkpoint_size;
+use ai2048::notation::Position;
+use ai2048::protocol;
+use ai2048::replay::Replay;
+use ai2048::server;
+use ai2048::server::Server;
+use ai2048::session::GameSession;
+use ai2048::session::SavedGame;
+use ai2048::stats::Stats;
+use ai2048::svg;
+use ai2048::training;
+use ai2048::training::PrintObserver;
+use ai2048::training::TrainingConfig;
+use ai2048::tui;
+use ai2048::tui::CastTiming;
+use ai2048::tui::TrainingDashboard;
+use ai2048::with_board_size;
 use burn::backend::Autodiff;
 use burn::backend::NdArray;
 use burn::backend::ndarray::NdArrayDevice;
@@ -9,47 +42,223 @@ use clap::Parser;
 
 use crate::cli::Cli;
 use crate::cli::Commands;
-use crate::model_structs::PolicyNet;
-use crate::model_structs::PolicyNetConfig;
-
-mod game_structs;
-mod game_traits;
-
-mod model_structs;
-mod model_traits;
-
-mod training;
 
 mod cli;
-mod tui;
 
 /// Currently, main is just "run 2048 in the terminal"
 /// It will be replaced by something more sophisticated in the future
 fn main() -> io::Result<()> {
     let cli = Cli::parse();
 
-    println!("Received command {:?}", cli.command);
+    // stdout belongs to the protocol, so nothing else can be printed
+    if let Commands::Engine = cli.command {
+        return protocol::run_engine(io::stdin().lock(), io::stdout().lock(), cli.size.unwrap_or(DEFAULT_SIZE));
+    }
+
+    eprintln!("Received command {:?}", cli.command);
+
+    tui::set_theme(cli.theme);
+
+    if let Commands::Stats { stats_file } = &cli.command {
+        return print_stats(stats_file.as_deref(), cli.size);
+    }
+
+    if let Commands::Serve { port, model } = &cli.command {
+        return serve(*port, model.as_deref(), cli.size.unwrap_or(DEFAULT_SIZE));
+    }
+
+    let size = board_size(&cli)?;
+    with_board_size!(size, N => run::<N>(cli.command))
+}
+
+/// The size from --size. Without it, replays and saved games use the size they were made at, and
+/// model agents are played at the size they were trained for; anything else is 4x4.
+fn board_size(cli: &Cli) -> io::Result<usize> {
+    // replays and saved games can only be shown at the size they were made at
+    let recorded = match &cli.command {
+        Commands::Replay { file } | Commands::Cast { file, .. } | Commands::Render { replay: Some(file), .. } => {
+            Some((file, Replay::load(file)?.size))
+        }
+        Commands::Play { resume: Some(file), .. } => Some((file, SavedGame::load(file)?.size)),
+        Commands::Play { from: Some(position), .. }
+        | Commands::AutoPlay { from: Some(position), .. }
+        | Commands::Render { board: Some(position), .. }
+        | Commands::Analyze { board: position, .. } => Some((&position.to_string(), position.size())),
+        _ => None,
+    };
+    if let Some((file, recorded)) = recorded {
+        return match cli.size {
+            Some(size) if size != recorded => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{file} is a {recorded}x{recorded} game, but --size {size} was given"),
+            )),
+            _ => Ok(recorded),
+        };
+    }
+
+    if let Some(size) = cli.size {
+        return Ok(size);
+    }
+
+    let agents: Vec<&AgentSpec> = match &cli.command {
+        Commands::Play { agent, .. } => agent.iter().collect(),
+        Commands::AutoPlay { agent, .. } => vec![agent],
+        Commands::Compare { agents, .. } => agents.iter().collect(),
+        Commands::Replay { .. }
+        | Commands::Analyze { .. }
+        | Commands::Cast { .. }
+        | Commands::Render { .. }
+        | Commands::Stats { .. }
+        | Commands::Engine
+        | Commands::Serve { .. }
+        | Commands::Train { .. } => vec![],
+    };
+    let model_path = agents.into_iter().find_map(|spec| match spec {
+        AgentSpec::Model(path) | AgentSpec::Mcts(_, Some(path)) => Some(path),
+        _ => None,
+    });
+
+    match model_path {
+        Some(path) => checkpoint_size(path),
+        None => Ok(DEFAULT_SIZE),
+    }
+}
 
-    match cli.command {
-        Commands::Play { seed } => {
+fn run<const N: usize>(command: Commands) -> io::Result<()> {
+    match command {
+        Commands::Play {
+            seed,
+            agent,
+            record,
+            spawn,
+            stats_file,
+            resume,
+            save_file,
+            from,
+        } => {
             println!("Starting interactive 2048...");
-            if let Some(s) = seed {
+            if let Some(path) = &resume {
+                println!("Resuming the game saved in {path}");
+            } else if let Some(s) = seed {
                 println!("Using PRNG seed {s}");
             }
 
-            tui::play::<4>(seed)?;
+            let session = match &resume {
+                Some(path) => GameSession::from_saved(&SavedGame::load(path)?)?,
+                None => new_session::<N>(from.as_ref(), seed, spawn, "human")?,
+            };
+            let spawn = session.rng.state().spawn_policy;
+
+            let hint_agent = agent.map(|spec| spec.build::<N>(spawn)).transpose()?;
+
+            // only finished games count; one that was saved and quit will count once it's done.
+            // Puzzles don't count at all, since they didn't start from scratch.
+            let session = tui::play::<N>(session, &save_file, hint_agent, |session| {
+                if session.replay.from_position {
+                    return Ok(vec![]);
+                }
+                let game = session.game;
+                let path = stats_path(stats_file.as_deref());
+                let mut stats = Stats::load(&path)?;
+                let lines = stats
+                    .record_game(N, spawn, game.current_score(), game.highest_tile())
+                    .summary_lines();
+                stats.save(&path)?;
+                Ok(lines)
+            })?;
+            save_recording(&session.replay, record.as_deref())?;
         }
 
-        Commands::AutoPlay { seed } => {
+        Commands::AutoPlay {
+            seed,
+            agent,
+            record,
+            spawn,
+            from,
+        } => {
             println!("Starting automatic 2048...");
             if let Some(s) = seed {
                 println!("Using PRNG seed {s}");
             }
 
-            let device = NdArrayDevice::default();
-            let model: PolicyNet<4, NdArray> = PolicyNetConfig::new().init(&device);
+            let mut agent = agent.build::<N>(spawn)?;
+
+            let session = new_session::<N>(from.as_ref(), seed, spawn, agent.name())?;
+            let session = tui::simulate(session, agent.as_mut())?;
+            save_recording(&session.replay, record.as_deref())?;
+        }
 
-            tui::simulate(seed, &model, &device)?;
+        Commands::Replay { file } => {
+            let replay = Replay::load(&file)?;
+            tui::view_replay::<N>(&replay)?;
+        }
+
+        Commands::Analyze {
+            board,
+            model,
+            depth,
+            rollouts,
+            rollout_policy,
+            seed,
+            spawn,
+            json,
+        } => {
+            let game = board.to_state::<N>().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            let mut model = model.map(|path| AgentSpec::Model(path).build::<N>(spawn)).transpose()?;
+            let options = AnalysisOptions {
+                depth,
+                rollouts,
+                rollout_policy,
+                seed,
+                spawn_policy: spawn,
+            };
+
+            let analysis = analysis::analyze(&game, &options, model.as_mut());
+            if json {
+                println!("{}", serde_json::to_string_pretty(&analysis)?);
+            } else {
+                for line in analysis.table_lines() {
+                    println!("{line}");
+                }
+            }
+        }
+
+        Commands::Cast {
+            file,
+            out,
+            move_delay,
+            end_delay,
+        } => {
+            let replay = Replay::load(&file)?;
+            let timing = CastTiming {
+                move_delay: Duration::from_millis(move_delay),
+                end_delay: Duration::from_millis(end_delay),
+            };
+            tui::write_cast::<N, _>(&replay, &replay.states()?, timing, BufWriter::new(File::create(&out)?))?;
+            println!("Recording of {} moves saved to {out}", replay.num_moves());
+        }
+
+        Commands::Render {
+            board,
+            replay,
+            out,
+            arrow,
+            frames,
+            columns,
+        } => render::<N>(board.as_ref(), replay.as_deref(), &out, arrow, frames, columns)?,
+
+        Commands::Stats { .. } | Commands::Engine | Commands::Serve { .. } => {
+            unreachable!("these don't depend on the board size, so they're handled in main")
+        }
+
+        Commands::Compare { seed, spawn, agents } => {
+            if agents.len() < 2 {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Need at least two agents to compare"));
+            }
+
+            let agents = agents.iter().map(|spec| spec.build::<N>(spawn)).collect::<io::Result<Vec<_>>>()?;
+
+            tui::compare(seed, spawn, agents)?;
         }
 
         Commands::Train {
@@ -60,26 +269,142 @@ fn main() -> io::Result<()> {
             learning_steps_per_batch,
             discount_factor,
             l2_reg,
+            reward,
+            alphazero,
+            dashboard,
         } => {
             println!("Starting model training");
             println!("Model will be saved in {output}");
 
-            let device = NdArrayDevice::default();
-            let mut model: PolicyNet<4, Autodiff<NdArray>> = PolicyNetConfig::new().init(&device);
-
-            training::train(
-                &mut model,
-                max_time,
+            let config = TrainingConfig {
+                max_time_sec: max_time,
                 learning_rate,
                 games_per_batch,
                 learning_steps_per_batch,
                 discount_factor,
                 l2_reg,
-            );
+                reward_weights: reward.into(),
+                mode: alphazero.into(),
+            };
+            println!("Reward weights: {:?}", config.reward_weights);
+            println!("Training mode: {:?}", config.mode);
+
+            let device = NdArrayDevice::default();
+            let mut model: PolicyNet<N, Autodiff<NdArray>> = PolicyNetConfig::new().init(&device);
+
+            if dashboard {
+                let mut dashboard = TrainingDashboard::start(&config)?;
+                training::train(&mut model, &config, &mut dashboard)?;
+            } else {
+                training::train(&mut model, &config, &mut PrintObserver)?;
+            }
+
+            model.save(&output)?;
+            println!("Model saved to {output}");
 
-            tui::simulate(None, &model, &device)?;
+            let mut agent = ModelAgent::new(model, device, format!("PolicyNet trained for {max_time}s"));
+            let session = GameSession::new(rand::random(), SpawnPolicy::default(), agent.name());
+            tui::simulate(session, &mut agent)?;
         }
     }
 
     Ok(())
 }
+
+fn save_recording(replay: &Replay, path: Option<&str>) -> io::Result<()> {
+    if let Some(path) = path {
+        replay.save(path)?;
+        println!("Recording of {} moves saved to {path}", replay.num_moves());
+    }
+    Ok(())
+}
+
+/// Write SVGs for a position, or for a replay as one contact sheet or a file per position
+fn render<const N: usize>(
+    board: Option<&Position>,
+    replay: Option<&str>,
+    out: &str,
+    arrow: Option<Move>,
+    frames: bool,
+    columns: usize,
+) -> io::Result<()> {
+    if let Some(position) = board {
+        let game = position
+            .to_state::<N>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        fs::write(out, svg::render_board(&game, arrow))?;
+        println!("Board saved to {out}");
+        return Ok(());
+    }
+
+    let path = replay.expect("clap requires --board or --replay");
+    let replay = Replay::load(path)?;
+    let states = replay.states::<N>()?;
+    if frames {
+        let paths = svg::frame_paths(out, states.len());
+        for (path, frame) in paths.iter().zip(svg::render_frames(&replay, &states)) {
+            fs::write(path, frame)?;
+        }
+        println!("{} frames saved to {} through {}", paths.len(), paths[0], paths[paths.len() - 1]);
+    } else {
+        fs::write(out, svg::render_contact_sheet(&replay, &states, columns))?;
+        println!("Contact sheet of {} positions saved to {out}", states.len());
+    }
+    Ok(())
+}
+
+fn stats_path(path: Option<&str>) -> PathBuf {
+    path.map(PathBuf::from).unwrap_or_else(Stats::default_path)
+}
+
+fn print_stats(path: Option<&str>, size: Option<usize>) -> io::Result<()> {
+    let path = stats_path(path);
+    let stats = Stats::load(&path)?;
+    let boards: Vec<_> = stats.boards.iter().filter(|b| size.is_none_or(|size| b.size == size)).collect();
+
+    if boards.is_empty() {
+        match size {
+            Some(size) => println!("No {size}x{size} games recorded in {} yet", path.display()),
+            None => println!("No games recorded in {} yet", path.display()),
+        }
+    }
+    for board in boards {
+        for line in board.summary_lines() {
+            println!("{line}");
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn serve(port: u16, model: Option<&str>, default_size: usize) -> io::Result<()> {
+    let model = model.map(server::load_policy_model).transpose()?;
+    if let Some(model) = &model {
+        let size = model.size();
+        println!("Loaded a model for {size}x{size} boards");
+    }
+
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    println!("Serving games on {}", listener.local_addr()?);
+    server::serve(listener, Arc::new(Server::new(default_size, model)))
+}
+
+/// A new game, either from a random first piece or from the given position
+fn new_session<const N: usize>(
+    from: Option<&Position>,
+    seed: Option<u64>,
+    spawn: SpawnPolicy,
+    player: impl Into<String>,
+) -> io::Result<GameSession<N>> {
+    let seed = seed.unwrap_or_else(rand::random);
+    match from {
+        Some(position) => {
+            let game = position
+                .to_state::<N>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            Ok(GameSession::from_position(game, seed, spawn, player))
+        }
+        None => Ok(GameSession::new(seed, spawn, player)),
+    }
+}
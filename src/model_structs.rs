@@ -1,28 +1,42 @@
 //! Here's where we actually define our model. This will be a neural net built using Burn.
 
+use std::fs;
+use std::io;
+
 use burn::nn::Linear;
 use burn::nn::LinearConfig;
 use burn::nn::Relu;
 use burn::prelude::*;
+use burn::record::BinBytesRecorder;
+use burn::record::FullPrecisionSettings;
+use burn::record::Recorder;
 use burn::tensor::Tensor;
 use burn::tensor::backend::Backend;
 
+use crate::env::ObservationEncoder;
+use crate::env::OneHotEncoder;
+use crate::env::one_hot_channels;
 use crate::game_structs::GameState;
 use crate::game_structs::Move;
 use crate::game_traits::FullGame;
 use crate::model_traits::Model;
 use crate::model_traits::MoveResult;
 
+#[cfg(test)]
+mod tests;
+
+/// Settings for a new `PolicyNet`; there aren't any yet
 #[derive(Config, Debug)]
 pub struct PolicyNetConfig {}
 
 impl PolicyNetConfig {
+    /// A freshly initialized (random) network for an NxN board
     pub fn init<const N: usize, B: Backend>(&self, device: &B::Device) -> PolicyNet<N, B> {
         PolicyNet {
             inner: InnerModel {
                 // note: this does a good job initializing these things
                 // shared portion
-                linear1: LinearConfig::new(N * N * NUM_POSSIBLE_STATES_PER_CELL, N * N * N * N).init(device),
+                linear1: LinearConfig::new(N * N * one_hot_channels(N), N * N * N * N).init(device),
                 linear2: LinearConfig::new(N * N * N * N, N * N * N * N).init(device),
 
                 // head-specific portions
@@ -36,10 +50,74 @@ impl PolicyNetConfig {
     }
 }
 
+/// The actor-critic network for an NxN board: two shared layers, then a policy head with a
+/// logit per move and a value head
 pub struct PolicyNet<const N: usize, B: Backend> {
+    /// The layers themselves, which don't depend on the board size
     pub inner: InnerModel<B>,
 }
 
+type CheckpointRecorder = BinBytesRecorder<FullPrecisionSettings>;
+
+/// Checkpoint files start with this, then a format version byte and the board size
+const CHECKPOINT_MAGIC: &[u8] = b"AI2048";
+const CHECKPOINT_VERSION: u8 = 1;
+
+/// Board size recorded in a checkpoint's header, and the weights that follow it
+fn split_checkpoint(bytes: &[u8]) -> Result<(usize, &[u8]), String> {
+    let Some(rest) = bytes.strip_prefix(CHECKPOINT_MAGIC) else {
+        return Err("not an ai2048 checkpoint (missing header)".to_string());
+    };
+    match rest {
+        [CHECKPOINT_VERSION, size, weights @ ..] => Ok((*size as usize, weights)),
+        [version, ..] => Err(format!("unsupported checkpoint version {version}")),
+        [] => Err("truncated header".to_string()),
+    }
+}
+
+/// Board size a checkpoint was trained for, without loading the weights
+pub fn checkpoint_size(path: &str) -> io::Result<usize> {
+    let bytes = fs::read(path)?;
+    split_checkpoint(&bytes)
+        .map(|(size, _)| size)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("Could not read {path}: {e}")))
+}
+
+impl<const N: usize, B: Backend> PolicyNet<N, B> {
+    /// Save the model weights to a checkpoint file, which records the board size
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let weights = CheckpointRecorder::default()
+            .record(self.inner.clone().into_record(), ())
+            .map_err(|e| io::Error::other(format!("Could not serialize model: {e}")))?;
+
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+        bytes.extend([CHECKPOINT_VERSION, N as u8]);
+        bytes.extend(weights);
+
+        fs::write(path, bytes)
+    }
+
+    /// Load a model from a checkpoint file written by `save`. Fails if the checkpoint is for a
+    /// different board size.
+    pub fn load(path: &str, device: &B::Device) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        let (size, weights) = split_checkpoint(&bytes).map_err(|e| invalid(format!("Could not load model from {path}: {e}")))?;
+        if size != N {
+            return Err(invalid(format!("Model in {path} is for a {size}x{size} board, not {N}x{N}")));
+        }
+
+        let record = Recorder::<B>::load(&CheckpointRecorder::default(), weights.to_vec(), device)
+            .map_err(|e| io::Error::other(format!("Could not load model from {path}: {e}")))?;
+
+        let mut out: Self = PolicyNetConfig::new().init(device);
+        out.inner = out.inner.load_record(record);
+        Ok(out)
+    }
+}
+
+/// `PolicyNet`'s layers, as a burn module
 #[derive(Module, Debug)]
 pub struct InnerModel<B: Backend> {
     // shared portion
@@ -71,24 +149,9 @@ impl<B: Backend> InnerModel<B> {
     }
 }
 
-pub const NUM_POSSIBLE_STATES_PER_CELL: usize = 18;
-
 impl<const N: usize, B: Backend> Model<N, B> for PolicyNet<N, B> {
     fn input_to_tensor(&self, state: &GameState<N>, device: &B::Device) -> Tensor<B, 1> {
-        let mut inputs: Vec<f32> = Vec::with_capacity(N * N * NUM_POSSIBLE_STATES_PER_CELL);
-        for y in 0..N {
-            for x in 0..N {
-                let actual_val = state.get_val(x, y);
-                for val in 0..(NUM_POSSIBLE_STATES_PER_CELL as u8) {
-                    if val == actual_val {
-                        inputs.push(1.0);
-                    } else {
-                        inputs.push(0.0);
-                    }
-                }
-            }
-        }
-
+        let inputs = OneHotEncoder.encode(state);
         let input = Tensor::<B, 1>::from_data(inputs.as_slice(), device);
 
         input
@@ -0,0 +1,54 @@
+use std::env;
+use std::fs;
+
+use burn::backend::NdArray;
+use burn::backend::ndarray::NdArrayDevice;
+
+use crate::game_structs::GameState;
+use crate::game_structs::RngPlacement;
+use crate::model_structs::PolicyNet;
+use crate::model_structs::PolicyNetConfig;
+use crate::model_structs::checkpoint_size;
+use crate::model_traits::Model;
+
+fn temp_path(name: &str) -> String {
+    env::temp_dir()
+        .join(format!("ai2048-{}-{name}", std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn test_checkpoint_round_trip_records_size() {
+    let device = NdArrayDevice::default();
+    let path = temp_path("round-trip.bin");
+
+    let model: PolicyNet<3, NdArray> = PolicyNetConfig::new().init(&device);
+    model.save(&path).unwrap();
+
+    assert_eq!(checkpoint_size(&path).unwrap(), 3);
+
+    let loaded: PolicyNet<3, NdArray> = PolicyNet::load(&path, &device).unwrap();
+    let state = GameState::<3>::new_random(&mut RngPlacement::new_from_seed(5));
+    let (expected, _) = model.get_output_tensor(model.input_to_tensor(&state, &device));
+    let (actual, _) = loaded.get_output_tensor(loaded.input_to_tensor(&state, &device));
+    assert_eq!(expected.into_data(), actual.into_data());
+
+    let err = PolicyNet::<4, NdArray>::load(&path, &device).err().unwrap();
+    assert!(err.to_string().contains("3x3 board, not 4x4"), "{err}");
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_load_rejects_files_without_header() {
+    let device = NdArrayDevice::default();
+    let path = temp_path("garbage.bin");
+    fs::write(&path, b"definitely not a model").unwrap();
+
+    assert!(checkpoint_size(&path).is_err());
+    let err = PolicyNet::<4, NdArray>::load(&path, &device).err().unwrap();
+    assert!(err.to_string().contains("missing header"), "{err}");
+
+    fs::remove_file(&path).unwrap();
+}
@@ -1,9 +1,12 @@
+//! What the training loop and model agent need from a network, independent of its architecture
+
 use burn::prelude::Backend;
 use burn::prelude::Tensor;
 
 use crate::game_structs::GameState;
 use crate::game_structs::Move;
 
+/// A network with a policy head and a value head over a board
 pub trait Model<const N: usize, B: Backend> {
     /// Converts input to a tensor
     fn input_to_tensor(&self, state: &GameState<N>, device: &B::Device) -> Tensor<B, 1>;
@@ -14,6 +17,7 @@ pub trait Model<const N: usize, B: Backend> {
     /// Given an output tensor, compute the move it corresponds to
     fn get_move_from_output(&self, state: &GameState<N>, output: Tensor<B, 1>) -> MoveResult;
 
+    /// Run the model on `state` and pick its move
     fn get_next_move(&self, state: &GameState<N>, device: &B::Device) -> MoveResult {
         let input_tensor = self.input_to_tensor(state, device);
         let (actor_logits, _critic_value) = self.get_output_tensor(input_tensor);
@@ -22,9 +26,10 @@ pub trait Model<const N: usize, B: Backend> {
     }
 }
 
+/// A move picked from the policy head's output
 pub struct MoveResult {
+    /// The highest-ranked legal move
     pub next_move: Move,
-    // if the first choice was illegal but the second was legal, this is 1
-    // values go from zero to three
+    /// How many higher-ranked moves were illegal: 0 if the first choice was legal, at most 3
     pub num_illegal_choices: u8,
 }
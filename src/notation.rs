@@ -0,0 +1,129 @@
+//! Text notation for board positions, so they can be written by hand on the command line.
+//!
+//! Rows go from top to bottom, separated by `/`; tiles within a row are separated by commas
+//! or spaces, and empty squares are `.` (or `0`). The score can be given after a `:`; if it's
+//! left off, it's estimated from the tiles. For example, a 4x4 position with a score of 60:
+//!
+//! ```text
+//! 2,4,.,./.,.,8,./.,.,.,./.,16,.,2:60
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::game_structs::GameState;
+use crate::game_traits::FullGame;
+
+#[cfg(test)]
+mod tests;
+
+/// A board written out in board notation; see the module docs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// Tile values (not exponents), row by row from the top; 0 for empty
+    pub rows: Vec<Vec<u32>>,
+    /// Score if given; otherwise `score` estimates it
+    pub given_score: Option<u32>,
+}
+
+impl Position {
+    /// Write out `state`, score included
+    pub fn from_state<const N: usize>(state: &GameState<N>) -> Self {
+        Self {
+            rows: state.tile_values(),
+            given_score: Some(state.current_score()),
+        }
+    }
+
+    /// Board size (N for an NxN board)
+    pub fn size(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The given score, or else the score the board would have if every tile had been built
+    /// up from spawned 2s. Spawned 4s skip a merge, so the real score could be a little lower.
+    pub fn score(&self) -> u32 {
+        self.given_score.unwrap_or_else(|| {
+            self.rows
+                .iter()
+                .flatten()
+                .filter(|&&val| val >= 4)
+                .map(|&val| (val.trailing_zeros() - 1) * val)
+                .sum()
+        })
+    }
+
+    /// The position as a board, for an NxN game; errors if it's another size or holds an
+    /// invalid tile
+    pub fn to_state<const N: usize>(&self) -> Result<GameState<N>, String> {
+        if self.size() != N {
+            return Err(format!("Position is for a {0}x{0} board, not {N}x{N}", self.size()));
+        }
+        GameState::from_tile_values(&self.rows, self.score())
+    }
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (board, score) = match s.split_once(':') {
+            Some((board, score)) => {
+                let score = score.trim().parse().map_err(|_| format!("Invalid score '{}'", score.trim()))?;
+                (board, Some(score))
+            }
+            None => (s, None),
+        };
+
+        let rows = board
+            .split('/')
+            .map(|row| {
+                row.split([',', ' '])
+                    .filter(|tile| !tile.is_empty())
+                    .map(|tile| match tile {
+                        "." | "0" => Ok(0),
+                        tile => match tile.parse::<u32>() {
+                            Ok(val) if val >= 2 && val.is_power_of_two() => Ok(val),
+                            _ => Err(format!("'{tile}' is not a valid tile")),
+                        },
+                    })
+                    .collect::<Result<Vec<u32>, String>>()
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let size = rows.len();
+        if let Some(row) = rows.iter().find(|row| row.len() != size) {
+            return Err(format!(
+                "Every row needs {size} tiles to match the {size} rows, but one has {}",
+                row.len()
+            ));
+        }
+        if rows.iter().flatten().all(|&val| val == 0) {
+            return Err("Position has no tiles".to_string());
+        }
+
+        Ok(Self { rows, given_score: score })
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let tiles: Vec<String> = row
+                    .iter()
+                    .map(|&val| if val == 0 { ".".to_string() } else { val.to_string() })
+                    .collect();
+                tiles.join(",")
+            })
+            .collect();
+
+        write!(f, "{}", rows.join("/"))?;
+        if let Some(score) = self.given_score {
+            write!(f, ":{score}")?;
+        }
+        Ok(())
+    }
+}
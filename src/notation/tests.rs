@@ -0,0 +1,54 @@
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_traits::FullGame;
+use crate::notation::Position;
+
+#[test]
+fn test_parse_position() {
+    let position: Position = "2,4,.,./.,.,8,./0 0 0 0/.,16,.,2:60".parse().unwrap();
+    assert_eq!(position.size(), 4);
+    assert_eq!(position.given_score, Some(60));
+
+    let state: GameState<4> = position.to_state().unwrap();
+    assert_eq!(state.get_val(0, 0), 1);
+    assert_eq!(state.get_val(1, 0), 2);
+    assert_eq!(state.get_val(2, 1), 3);
+    assert_eq!(state.get_val(1, 3), 4);
+    assert_eq!(state.get_val(3, 3), 1);
+    assert_eq!(state.empty_cells().len(), 11);
+    assert_eq!(state.current_score(), 60);
+
+    assert!(position.to_state::<5>().is_err(), "wrong size");
+}
+
+#[test]
+fn test_estimated_score() {
+    let position: Position = "2,4/8,16".parse().unwrap();
+    // 4 = 2+2 (4 points); 8 = two 4s (16); 16 = two 8s (48)
+    assert_eq!(position.score(), 4 + 16 + 48);
+
+    let position: Position = "2,2/2,.".parse().unwrap();
+    assert_eq!(position.score(), 0);
+}
+
+#[test]
+fn test_bad_positions() {
+    for bad in ["", "2,4/8", "2,3/4,8", "2,4/8,16:lots", ".,./.,.", "2,4,8/16,32,64"] {
+        assert!(bad.parse::<Position>().is_err(), "{bad:?} should not parse");
+    }
+}
+
+#[test]
+fn test_display_round_trip() {
+    let mut rng = RngPlacement::new_from_seed(8);
+    let mut state = GameState::<5>::new_random(&mut rng);
+    for _ in 0..3 {
+        state = state.apply_move(Move::Left, &mut rng).unwrap_or(state);
+    }
+
+    let text = Position::from_state(&state).to_string();
+    let parsed: Position = text.parse().unwrap();
+    assert_eq!(parsed.to_state::<5>().unwrap(), state);
+    assert_eq!("2,.,4/.,.,./.,.,8".parse::<Position>().unwrap().to_string(), "2,.,4/.,.,./.,.,8");
+}
@@ -0,0 +1,52 @@
+//! The tile colors of the original web game, carried on through purples and blues for bigger
+//! tiles. Shared by the true-color terminal theme and the SVG export.
+
+/// An sRGB color
+pub type Rgb = (u8, u8, u8);
+
+/// Tile backgrounds indexed by exponent, up to 2^17
+const TILE_BACKGROUNDS: [Rgb; 18] = [
+    (205, 193, 180), // empty
+    (238, 228, 218), // 2
+    (237, 224, 200), // 4
+    (242, 177, 121), // 8
+    (245, 149, 99),  // 16
+    (246, 124, 95),  // 32
+    (246, 94, 59),   // 64
+    (237, 207, 114), // 128
+    (237, 204, 97),  // 256
+    (237, 200, 80),  // 512
+    (237, 197, 63),  // 1024
+    (237, 194, 46),  // 2048
+    (176, 110, 200), // 4096
+    (142, 91, 181),  // 8192
+    (106, 79, 176),  // 16384
+    (74, 86, 176),   // 32768
+    (52, 116, 168),  // 65536
+    (42, 143, 143),  // 131072
+];
+
+/// Anything past the end of the palette
+const BEYOND: Rgb = (60, 58, 50);
+
+/// The board itself, between the cells
+pub const GRID: Rgb = (187, 173, 160);
+/// Numbers on the small, light tiles
+pub const DARK_TEXT: Rgb = (119, 110, 101);
+/// Numbers on the bigger, darker tiles
+pub const LIGHT_TEXT: Rgb = (249, 246, 242);
+
+/// Background of a cell holding the given exponent (0 for empty)
+pub fn tile_background(exponent: u8) -> Rgb {
+    TILE_BACKGROUNDS.get(exponent as usize).copied().unwrap_or(BEYOND)
+}
+
+/// Text color for a cell holding the given exponent: dark on the pale 2 and 4 tiles, light on
+/// the rest, and the grid color for the dot in an empty cell
+pub fn tile_text(exponent: u8) -> Rgb {
+    match exponent {
+        0 => GRID,
+        1 | 2 => DARK_TEXT,
+        _ => LIGHT_TEXT,
+    }
+}
@@ -0,0 +1,316 @@
+//! Line-delimited JSON protocol for playing the engine from other programs. Each request is one
+//! JSON object on its own line, and gets exactly one JSON line back.
+//!
+//! Requests are tagged by `cmd`:
+//!
+//! ```text
+//! {"cmd":"new_game","size":4,"seed":7,"spawn_policy":"classic"}   (every field optional)
+//! {"cmd":"state"}
+//! {"cmd":"legal_moves"}
+//! {"cmd":"move","move":"left"}
+//! ```
+//!
+//! Responses always have `ok`, plus `error` if it's false. `new_game`, `state` and `move` return
+//! the position as `state` (size, board as tile values, score, game_over, legal_moves); `move`
+//! also returns the `spawn` that followed and the `score_delta`, and `legal_moves` returns just
+//! `legal_moves`.
+//!
+//! External agents speak the other side of the same protocol: they're sent a `state` response
+//! whenever it's their turn, may ask for `state` or `legal_moves`, and answer with a `move`.
+
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+use crate::notation::Position;
+use crate::replay::Spawn;
+use crate::session::GameSession;
+use crate::with_board_size;
+
+#[cfg(test)]
+mod tests;
+
+/// A request, as a client sends it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Start a new game, replacing any in progress
+    NewGame {
+        /// Board size; the engine's default if left off
+        #[serde(default)]
+        size: Option<usize>,
+        /// Seed for the spawns; random if left off
+        #[serde(default)]
+        seed: Option<u64>,
+        /// How pieces spawn; even if left off
+        #[serde(default)]
+        spawn_policy: Option<SpawnPolicy>,
+    },
+    /// The current position
+    State,
+    /// Just the legal moves from the current position
+    LegalMoves,
+    /// Make a move
+    Move {
+        /// Which way to slide
+        #[serde(rename = "move")]
+        m: Move,
+    },
+}
+
+/// The answer to a request; every field but `ok` is left out when empty
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    /// Whether the request succeeded
+    pub ok: bool,
+    /// What went wrong, if it didn't
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The position, after the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<StateView>,
+    /// The piece placed after a move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn: Option<Spawn>,
+    /// Points scored by a move
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_delta: Option<u32>,
+    /// Answer to `legal_moves`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legal_moves: Option<Vec<Move>>,
+}
+
+impl Response {
+    /// Success, with nothing else to say
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    /// Failure, saying why
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(msg.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Success, with the position
+    pub fn with_state(state: StateView) -> Self {
+        Self {
+            ok: true,
+            state: Some(state),
+            ..Self::default()
+        }
+    }
+
+    /// Success, with the legal moves
+    pub fn with_legal_moves(legal_moves: Vec<Move>) -> Self {
+        Self {
+            ok: true,
+            legal_moves: Some(legal_moves),
+            ..Self::default()
+        }
+    }
+}
+
+/// A position as seen from outside
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StateView {
+    /// Board size (N for an NxN board)
+    pub size: usize,
+    /// Tile values (not exponents), row by row from the top; 0 for empty
+    pub board: Vec<Vec<u32>>,
+    /// Score so far
+    pub score: u32,
+    /// Whether there are no legal moves left
+    pub game_over: bool,
+    /// Moves that would change the board
+    pub legal_moves: Vec<Move>,
+}
+
+impl StateView {
+    /// Describe `game`
+    pub fn of<const N: usize>(game: &GameState<N>) -> Self {
+        Self {
+            size: N,
+            board: game.tile_values(),
+            score: game.current_score(),
+            game_over: game.is_finished(),
+            legal_moves: legal_moves(game),
+        }
+    }
+}
+
+/// Moves that would change the board, in `Move::ALL` order
+pub fn legal_moves<const N: usize>(game: &GameState<N>) -> Vec<Move> {
+    Move::ALL.into_iter().filter(|&m| game.is_legal_move(m)).collect()
+}
+
+/// A `GameSession` of whatever size, for code that only learns the size at runtime
+pub trait AnySession: Send {
+    /// The current position
+    fn view(&self) -> StateView;
+
+    /// Make a move, returning the piece that spawned and the score it earned
+    fn play(&mut self, m: Move) -> Result<(Spawn, u32), String>;
+
+    /// Take back the last move; see `GameSession::undo`
+    fn undo(&mut self) -> Result<(), String>;
+}
+
+impl<const N: usize> AnySession for GameSession<N> {
+    fn view(&self) -> StateView {
+        StateView::of(&self.game)
+    }
+
+    fn play(&mut self, m: Move) -> Result<(Spawn, u32), String> {
+        if self.game.is_finished() {
+            return Err("The game is over".to_string());
+        }
+        if !self.apply_move(m) {
+            return Err(format!("{m:?} is not a legal move"));
+        }
+
+        let step = self.replay.steps.last().expect("A move was just recorded");
+        Ok((step.spawn, step.score_delta))
+    }
+
+    fn undo(&mut self) -> Result<(), String> {
+        if GameSession::undo(self) {
+            Ok(())
+        } else {
+            Err("There are no moves to undo".to_string())
+        }
+    }
+}
+
+/// A new game of any supported size, starting from a single random piece
+pub fn new_any_session(size: usize, seed: u64, spawn_policy: SpawnPolicy, player: &str) -> io::Result<Box<dyn AnySession>> {
+    with_board_size!(size, N => {
+        let session: Box<dyn AnySession> = Box::new(GameSession::<N>::new(seed, spawn_policy, player));
+        Ok(session)
+    })
+}
+
+/// Like `new_any_session`, but starting from a given position instead of a single random piece
+pub fn any_session_from_position(
+    position: &Position,
+    seed: u64,
+    spawn_policy: SpawnPolicy,
+    player: &str,
+) -> io::Result<Box<dyn AnySession>> {
+    with_board_size!(position.size(), N => {
+        let game = position
+            .to_state::<N>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let session: Box<dyn AnySession> = Box::new(GameSession::<N>::from_position(game, seed, spawn_policy, player));
+        Ok(session)
+    })
+}
+
+/// Make a move and describe the result: the new position, the spawn that followed and the score
+pub fn move_response(session: &mut dyn AnySession, m: Move) -> Response {
+    match session.play(m) {
+        Ok((spawn, score_delta)) => Response {
+            spawn: Some(spawn),
+            score_delta: Some(score_delta),
+            ..Response::with_state(session.view())
+        },
+        Err(e) => Response::error(e),
+    }
+}
+
+/// Answer a request about a single position; these are the only ones an external agent can make
+pub fn answer_query<const N: usize>(game: &GameState<N>, request: &Request) -> Option<Response> {
+    match request {
+        Request::State => Some(Response::with_state(StateView::of(game))),
+        Request::LegalMoves => Some(Response::with_legal_moves(legal_moves(game))),
+        Request::NewGame { .. } | Request::Move { .. } => None,
+    }
+}
+
+/// One client's game, driven by protocol requests
+pub struct Engine {
+    session: Option<Box<dyn AnySession>>,
+    default_size: usize,
+}
+
+impl Engine {
+    /// An engine with no game yet; `new_game` without a size makes a `default_size` game
+    pub fn new(default_size: usize) -> Self {
+        Self {
+            session: None,
+            default_size,
+        }
+    }
+
+    /// Answer one request
+    pub fn handle(&mut self, request: Request) -> Response {
+        if let Request::NewGame { size, seed, spawn_policy } = request {
+            let size = size.unwrap_or(self.default_size);
+            let seed = seed.unwrap_or_else(rand::random);
+            return match new_any_session(size, seed, spawn_policy.unwrap_or_default(), "engine client") {
+                Ok(session) => {
+                    let response = Response::with_state(session.view());
+                    self.session = Some(session);
+                    response
+                }
+                Err(e) => Response::error(e.to_string()),
+            };
+        }
+
+        let Some(session) = self.session.as_mut() else {
+            return Response::error("No game in progress; send new_game first");
+        };
+
+        match request {
+            Request::NewGame { .. } => unreachable!("handled above"),
+            Request::State => Response::with_state(session.view()),
+            Request::LegalMoves => Response::with_legal_moves(session.view().legal_moves),
+            Request::Move { m } => move_response(session.as_mut(), m),
+        }
+    }
+}
+
+/// Read one line of JSON as a request
+pub fn parse_request(line: &str) -> Result<Request, String> {
+    serde_json::from_str(line).map_err(|e| format!("Invalid request: {e}"))
+}
+
+/// Write `message` as one line of JSON, and flush it
+pub fn write_message<W: Write, T: Serialize>(out: &mut W, message: &T) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, message)?;
+    out.write_all(b"\n")?;
+    out.flush()
+}
+
+/// Serve requests from `input` until it closes, writing responses to `output`
+pub fn run_engine<R: BufRead, W: Write>(input: R, mut output: W, default_size: usize) -> io::Result<()> {
+    let mut engine = Engine::new(default_size);
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match parse_request(&line) {
+            Ok(request) => engine.handle(request),
+            Err(e) => Response::error(e),
+        };
+        write_message(&mut output, &response)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use crate::agents::Agent;
+use crate::agents::ExternalAgent;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_traits::FullGame;
+use crate::protocol::Request;
+use crate::protocol::Response;
+use crate::protocol::run_engine;
+
+/// Feed the engine these request lines and parse what comes back
+fn talk(lines: &[&str]) -> Vec<Response> {
+    let input = lines.join("\n");
+    let mut output = Vec::new();
+    run_engine(input.as_bytes(), &mut output, 4).unwrap();
+
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_request_format() {
+    let parsed: Request = serde_json::from_str(r#"{"cmd":"move","move":"left"}"#).unwrap();
+    assert_eq!(parsed, Request::Move { m: Move::Left });
+
+    let parsed: Request = serde_json::from_str(r#"{"cmd":"new_game","size":5}"#).unwrap();
+    assert_eq!(
+        parsed,
+        Request::NewGame {
+            size: Some(5),
+            seed: None,
+            spawn_policy: None
+        }
+    );
+}
+
+#[test]
+fn test_engine_session() {
+    let responses = talk(&[
+        r#"{"cmd":"state"}"#,
+        r#"{"cmd":"new_game","size":3,"seed":11,"spawn_policy":"classic"}"#,
+        r#"{"cmd":"legal_moves"}"#,
+        "",
+        r#"{"cmd":"state"}"#,
+        "not json",
+    ]);
+    assert_eq!(responses.len(), 5, "blank lines are skipped");
+
+    assert!(!responses[0].ok, "no game yet");
+    assert!(responses[0].error.as_ref().unwrap().contains("new_game"));
+
+    let state = responses[1].state.as_ref().unwrap();
+    assert_eq!(state.size, 3);
+    assert_eq!(state.board.iter().flatten().filter(|&&v| v != 0).count(), 1);
+    assert!(!state.game_over);
+
+    assert_eq!(responses[2].legal_moves.as_ref(), Some(&state.legal_moves));
+    assert_eq!(responses[3].state.as_ref(), Some(state));
+    assert!(!responses[4].ok);
+}
+
+#[test]
+fn test_engine_moves() {
+    let mut lines = vec![r#"{"cmd":"new_game","seed":3}"#.to_string()];
+    for m in ["up", "left", "down", "right"] {
+        lines.push(format!(r#"{{"cmd":"move","move":"{m}"}}"#));
+    }
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let responses = talk(&lines);
+
+    let mut previous = responses[0].state.clone().unwrap();
+    for response in &responses[1..] {
+        if !response.ok {
+            // only illegal moves fail, and they leave the game alone
+            assert!(response.error.as_ref().unwrap().contains("not a legal move"));
+            continue;
+        }
+
+        let state = response.state.clone().unwrap();
+        let spawn = response.spawn.unwrap();
+        assert_eq!(state.board[spawn.y][spawn.x], spawn.value);
+        assert_eq!(state.score, previous.score + response.score_delta.unwrap());
+        previous = state;
+    }
+}
+
+#[test]
+fn test_engine_matches_local_game() {
+    // the same seed gives the same game as playing locally
+    let responses = talk(&[r#"{"cmd":"new_game","seed":21}"#, r#"{"cmd":"move","move":"down"}"#]);
+
+    let mut rng = RngPlacement::new_from_seed(21);
+    let game = GameState::<4>::new_random(&mut rng);
+    assert_eq!(responses[0].state.as_ref().unwrap().board, game.tile_values());
+
+    if let Ok(next) = game.apply_move(Move::Down, &mut rng) {
+        assert_eq!(responses[1].state.as_ref().unwrap().board, next.tile_values());
+    }
+}
+
+#[test]
+fn test_external_agent() {
+    // asks for the legal moves, then plays the first one
+    let script = r#"while read state; do
+        echo '{"cmd":"legal_moves"}'
+        read legal
+        first=$(echo "$legal" | sed 's/.*"legal_moves":\["\([a-z]*\)".*/\1/')
+        echo "{\"cmd\":\"move\",\"move\":\"$first\"}"
+    done"#;
+    let mut agent = ExternalAgent::spawn(script).unwrap();
+
+    let mut rng = RngPlacement::new_from_seed(5);
+    let mut game = GameState::<4>::new_random(&mut rng);
+    for _ in 0..10 {
+        let expected = Move::ALL.into_iter().find(|&m| game.is_legal_move(m)).unwrap();
+        let m = Agent::<4>::choose_move(&mut agent, &game).unwrap();
+        assert_eq!(m, expected);
+        game = game.apply_move(m, &mut rng).unwrap();
+    }
+}
+
+#[test]
+fn test_external_agent_that_exits_is_an_error() {
+    let mut agent = ExternalAgent::spawn("read state; echo 'not json'; exit 1").unwrap();
+    let game = GameState::<4>::new_random(&mut RngPlacement::new_from_seed(5));
+
+    // the garbage gets an error response, and then the agent's output closes
+    let err = Agent::<4>::choose_move(&mut agent, &game).unwrap_err();
+    assert!(err.to_string().contains("External agent"), "{err}");
+}
@@ -0,0 +1,342 @@
+//! Python bindings, as an extension module called `ai2048` (behind the `python` feature).
+//!
+//! `GameState` is a single game with its own seeded spawns; `VecEnv` is a batch of games with
+//! a Gym-style `reset`/`step`. Boards and observations come back as nested lists, which
+//! `numpy.asarray` turns straight into arrays. Moves are indexed like `Move::ALL` (up, down,
+//! left, right), and `GameState` also accepts their names.
+//!
+//! To build the module and run its tests against the local interpreter, use `python/test.sh`.
+//! `cargo test --features python` runs the same tests with an embedded interpreter.
+
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::env::ExponentEncoder;
+use crate::env::ObservationEncoder;
+use crate::env::OneHotEncoder;
+use crate::env::VecEnv;
+use crate::env::VecObservation;
+use crate::env::VecStep;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::notation::Position;
+use crate::protocol::AnySession;
+use crate::protocol::any_session_from_position;
+use crate::protocol::new_any_session;
+use crate::rewards::RewardWeights;
+use crate::rewards::WeightedReward;
+use crate::with_board_size;
+
+#[cfg(test)]
+mod tests;
+
+#[pymodule]
+#[pyo3(name = "ai2048")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGameState>()?;
+    m.add_class::<PyVecEnv>()?;
+    Ok(())
+}
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn parse_spawn_policy(s: &str) -> PyResult<SpawnPolicy> {
+    s.parse().map_err(value_error)
+}
+
+fn move_from_idx(idx: usize) -> PyResult<Move> {
+    if idx < Move::ALL.len() {
+        Ok(Move::from_idx(idx))
+    } else {
+        Err(value_error(format!("Invalid move {idx}; moves are 0 to 3 (up, down, left, right)")))
+    }
+}
+
+fn move_name(m: Move) -> String {
+    format!("{m:?}").to_lowercase()
+}
+
+/// A game with its own spawn RNG, playable from Python
+#[pyclass(name = "GameState", unsendable)]
+struct PyGameState {
+    session: Box<dyn AnySession>,
+}
+
+#[pymethods]
+impl PyGameState {
+    /// A new game with a single random piece, or starting from `position` in board notation
+    #[new]
+    #[pyo3(signature = (size = 4, seed = None, spawn_policy = "even", position = None))]
+    fn new(size: usize, seed: Option<u64>, spawn_policy: &str, position: Option<&str>) -> PyResult<Self> {
+        let seed = seed.unwrap_or_else(rand::random);
+        let spawn_policy = parse_spawn_policy(spawn_policy)?;
+        let session = match position {
+            Some(position) => {
+                let position: Position = position.parse().map_err(value_error)?;
+                any_session_from_position(&position, seed, spawn_policy, "python")
+            }
+            None => new_any_session(size, seed, spawn_policy, "python"),
+        };
+        Ok(Self {
+            session: session.map_err(value_error)?,
+        })
+    }
+
+    #[getter]
+    fn size(&self) -> usize {
+        self.session.view().size
+    }
+
+    /// Tile values (not exponents), row by row from the top; 0 for empty
+    #[getter]
+    fn board(&self) -> Vec<Vec<u32>> {
+        self.session.view().board
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.session.view().score
+    }
+
+    #[getter]
+    fn game_over(&self) -> bool {
+        self.session.view().game_over
+    }
+
+    #[getter]
+    fn highest_tile(&self) -> u32 {
+        self.session.view().board.into_iter().flatten().max().unwrap_or(0)
+    }
+
+    /// Names of the legal moves, in index order
+    fn legal_moves(&self) -> Vec<String> {
+        self.session.view().legal_moves.into_iter().map(move_name).collect()
+    }
+
+    /// Legal moves as four booleans, indexed like the moves
+    fn action_mask(&self) -> [bool; 4] {
+        let legal = self.session.view().legal_moves;
+        Move::ALL.map(|m| legal.contains(&m))
+    }
+
+    /// Make a move, given by name or index, and spawn a piece. Returns whether it was legal;
+    /// illegal moves change nothing.
+    #[pyo3(name = "move")]
+    fn make_move(&mut self, m: &Bound<'_, PyAny>) -> PyResult<bool> {
+        let m = if let Ok(idx) = m.extract::<usize>() {
+            move_from_idx(idx)?
+        } else {
+            let name: String = m.extract()?;
+            name.parse().map_err(value_error)?
+        };
+        Ok(self.session.play(m).is_ok())
+    }
+
+    /// Take back the last move, spawn included; returns whether there was one
+    fn undo(&mut self) -> bool {
+        self.session.undo().is_ok()
+    }
+
+    fn __repr__(&self) -> String {
+        let view = self.session.view();
+        let position = Position {
+            rows: view.board,
+            given_score: Some(view.score),
+        };
+        format!("GameState('{position}')")
+    }
+}
+
+/// Board encodings selectable from Python
+#[derive(Copy, Clone, Debug)]
+enum Encoding {
+    OneHot,
+    Exponent,
+}
+
+impl<const N: usize> ObservationEncoder<N> for Encoding {
+    fn num_features(&self) -> usize {
+        match self {
+            Encoding::OneHot => ObservationEncoder::<N>::num_features(&OneHotEncoder),
+            Encoding::Exponent => ObservationEncoder::<N>::num_features(&ExponentEncoder),
+        }
+    }
+
+    fn encode(&self, state: &GameState<N>) -> Vec<f32> {
+        match self {
+            Encoding::OneHot => OneHotEncoder.encode(state),
+            Encoding::Exponent => ExponentEncoder.encode(state),
+        }
+    }
+}
+
+/// A `VecEnv` of whatever size
+trait AnyVecEnv {
+    fn reset(&mut self, seed: Option<u64>) -> VecObservation;
+
+    fn step(&mut self, actions: &[Move]) -> VecStep;
+
+    fn num_envs(&self) -> usize;
+
+    fn num_features(&self) -> usize;
+}
+
+impl<const N: usize> AnyVecEnv for VecEnv<N, Encoding, WeightedReward<N>> {
+    fn reset(&mut self, seed: Option<u64>) -> VecObservation {
+        VecEnv::reset(self, seed)
+    }
+
+    fn step(&mut self, actions: &[Move]) -> VecStep {
+        VecEnv::step(self, actions)
+    }
+
+    fn num_envs(&self) -> usize {
+        VecEnv::num_envs(self)
+    }
+
+    fn num_features(&self) -> usize {
+        VecEnv::num_features(self)
+    }
+}
+
+fn reward_weights(weights: Option<HashMap<String, f32>>) -> PyResult<RewardWeights> {
+    let Some(weights) = weights else {
+        return Ok(RewardWeights::default());
+    };
+
+    let mut out = RewardWeights {
+        score_delta: 0.0,
+        log_merge: 0.0,
+        empty_cells: 0.0,
+        survival: 0.0,
+        game_over: 0.0,
+        max_tile: 0.0,
+    };
+    for (name, weight) in weights {
+        let slot = match name.as_str() {
+            "score_delta" => &mut out.score_delta,
+            "log_merge" => &mut out.log_merge,
+            "empty_cells" => &mut out.empty_cells,
+            "survival" => &mut out.survival,
+            "game_over" => &mut out.game_over,
+            "max_tile" => &mut out.max_tile,
+            _ => {
+                return Err(value_error(format!(
+                    "Unknown reward '{name}'; expected score_delta, log_merge, empty_cells, survival, game_over or max_tile"
+                )));
+            }
+        };
+        *slot = weight;
+    }
+    Ok(out)
+}
+
+/// Several games stepped together, Gym style. Finished games are reset automatically.
+#[pyclass(name = "VecEnv", unsendable)]
+struct PyVecEnv {
+    env: Box<dyn AnyVecEnv>,
+}
+
+#[pymethods]
+impl PyVecEnv {
+    /// `encoding` is "onehot" (one channel per exponent, per cell) or "exponent" (one number per
+    /// cell). `reward` maps reward names (see `rewards`) to weights, and defaults to the plain
+    /// score delta; rewards left out of it get no weight.
+    #[new]
+    #[pyo3(signature = (num_envs, size = 4, encoding = "onehot", reward = None, max_steps = None, spawn_policy = "even"))]
+    fn new(
+        num_envs: usize,
+        size: usize,
+        encoding: &str,
+        reward: Option<HashMap<String, f32>>,
+        max_steps: Option<usize>,
+        spawn_policy: &str,
+    ) -> PyResult<Self> {
+        let encoding = match encoding {
+            "onehot" => Encoding::OneHot,
+            "exponent" => Encoding::Exponent,
+            other => return Err(value_error(format!("Unknown encoding '{other}'; expected onehot or exponent"))),
+        };
+        let weights = reward_weights(reward)?;
+        let spawn_policy = parse_spawn_policy(spawn_policy)?;
+
+        let env = with_board_size!(size, N => {
+            let env = VecEnv::<N, _, _>::new(num_envs, encoding, weights.build::<N>()).with_spawn_policy(spawn_policy);
+            let env = match max_steps {
+                Some(max_steps) => env.with_max_steps(max_steps),
+                None => env,
+            };
+            let env: Box<dyn AnyVecEnv> = Box::new(env);
+            Ok(env)
+        })
+        .map_err(value_error)?;
+
+        Ok(Self { env })
+    }
+
+    #[getter]
+    fn num_envs(&self) -> usize {
+        self.env.num_envs()
+    }
+
+    #[getter]
+    fn num_features(&self) -> usize {
+        self.env.num_features()
+    }
+
+    #[getter]
+    fn num_actions(&self) -> usize {
+        Move::ALL.len()
+    }
+
+    /// Start every game over. Returns `(observations, info)`, where `info["action_mask"]` has
+    /// the legal moves of each game.
+    #[pyo3(signature = (seed = None))]
+    fn reset<'py>(&mut self, py: Python<'py>, seed: Option<u64>) -> PyResult<(Vec<Vec<f32>>, Bound<'py, PyDict>)> {
+        let observation = self.env.reset(seed);
+        let info = PyDict::new(py);
+        info.set_item("action_mask", &observation.action_masks)?;
+        Ok((rows(&observation), info))
+    }
+
+    /// Make one move (by index) in every game. Returns `(observations, rewards, terminated,
+    /// truncated, info)`; `info` maps each of action_mask, score, score_delta, highest_tile,
+    /// num_steps and illegal_move to a list with an entry per game.
+    #[allow(clippy::type_complexity)]
+    fn step<'py>(
+        &mut self,
+        py: Python<'py>,
+        actions: Vec<usize>,
+    ) -> PyResult<(Vec<Vec<f32>>, Vec<f32>, Vec<bool>, Vec<bool>, Bound<'py, PyDict>)> {
+        if actions.len() != self.env.num_envs() {
+            return Err(value_error(format!(
+                "Got {} actions for {} games",
+                actions.len(),
+                self.env.num_envs()
+            )));
+        }
+        let actions = actions.into_iter().map(move_from_idx).collect::<PyResult<Vec<_>>>()?;
+
+        let step = self.env.step(&actions);
+        let info = PyDict::new(py);
+        info.set_item("action_mask", &step.observation.action_masks)?;
+        info.set_item("score", step.infos.iter().map(|i| i.score).collect::<Vec<_>>())?;
+        info.set_item("score_delta", step.infos.iter().map(|i| i.score_delta).collect::<Vec<_>>())?;
+        info.set_item("highest_tile", step.infos.iter().map(|i| i.highest_tile).collect::<Vec<_>>())?;
+        info.set_item("num_steps", step.infos.iter().map(|i| i.num_steps).collect::<Vec<_>>())?;
+        info.set_item("illegal_move", step.infos.iter().map(|i| i.illegal_move).collect::<Vec<_>>())?;
+
+        Ok((rows(&step.observation), step.rewards, step.terminated, step.truncated, info))
+    }
+}
+
+/// Observations as one list per game
+fn rows(observation: &VecObservation) -> Vec<Vec<f32>> {
+    (0..observation.num_envs()).map(|i| observation.row(i).to_vec()).collect()
+}
@@ -0,0 +1,29 @@
+use std::ffi::CString;
+
+use pyo3::prelude::*;
+use pyo3::types::PyModule;
+
+use super::python_module;
+
+/// Runs python/test_ai2048.py against the module, without building it as an extension first
+#[test]
+fn test_python_suite() {
+    Python::initialize();
+    Python::attach(|py| -> PyResult<()> {
+        let module = PyModule::new(py, "ai2048")?;
+        python_module(&module)?;
+        py.import("sys")?.getattr("modules")?.set_item("ai2048", module)?;
+
+        let code = CString::new(include_str!("../../python/test_ai2048.py")).unwrap();
+        let tests = PyModule::from_code(py, &code, c"test_ai2048.py", c"test_ai2048")?;
+
+        let unittest = py.import("unittest")?;
+        let suite = unittest
+            .getattr("defaultTestLoader")?
+            .call_method1("loadTestsFromModule", (tests,))?;
+        let result = unittest.getattr("TextTestRunner")?.call0()?.call_method1("run", (suite,))?;
+        assert!(result.call_method0("wasSuccessful")?.extract::<bool>()?);
+        Ok(())
+    })
+    .unwrap();
+}
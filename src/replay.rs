@@ -0,0 +1,149 @@
+//! Recorded games. A replay is the starting position plus, for every move, the direction taken
+//! and the piece that spawned afterward, which is enough to reconstruct every position exactly.
+//! Replays are saved as JSON.
+
+use std::fs;
+use std::io;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_traits::FullGame;
+
+#[cfg(test)]
+pub(crate) mod tests;
+
+/// A whole game, as the moves and spawns that took it from its starting position
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    /// Board size (N for an NxN board)
+    pub size: usize,
+    /// Seed the spawns came from, if known
+    pub seed: Option<u64>,
+    /// Who was playing: "human" or an agent's name
+    pub player: String,
+    /// Tile values (not exponents) of the starting position, row by row
+    pub initial_board: Vec<Vec<u32>>,
+    /// Score of the starting position
+    pub initial_score: u32,
+    /// Whether the game started from a given position (e.g. `play --from`) rather than a single
+    /// random piece
+    #[serde(default)]
+    pub from_position: bool,
+    /// Every move, in order
+    pub steps: Vec<ReplayStep>,
+}
+
+/// One move and what came of it
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayStep {
+    /// The move made
+    #[serde(rename = "move")]
+    pub m: Move,
+    /// The piece placed after it
+    pub spawn: Spawn,
+    /// Points the move scored
+    pub score_delta: u32,
+}
+
+/// A piece placed after a move; `value` is the actual tile value (2 or 4)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spawn {
+    /// Column, from the left
+    pub x: usize,
+    /// Row, from the top
+    pub y: usize,
+    /// 2 or 4
+    pub value: u32,
+}
+
+impl Replay {
+    /// An empty recording of a game starting at `initial`
+    pub fn new<const N: usize>(initial: &GameState<N>, seed: Option<u64>, player: impl Into<String>) -> Self {
+        Self {
+            size: N,
+            seed,
+            player: player.into(),
+            initial_board: initial.tile_values(),
+            initial_score: initial.current_score(),
+            from_position: false,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Record that `m` took the game from `before` to `after` (which includes the spawned piece)
+    pub fn record<const N: usize>(&mut self, before: &GameState<N>, m: Move, after: &GameState<N>) {
+        let afterstate = before.slide(m);
+
+        let spawn = afterstate
+            .empty_cells()
+            .into_iter()
+            .find(|&(x, y)| after.get_val(x, y) != 0)
+            .map(|(x, y)| Spawn {
+                x,
+                y,
+                value: 1 << after.get_val(x, y),
+            })
+            .expect("A legal move should always be followed by a new piece");
+
+        self.steps.push(ReplayStep {
+            m,
+            spawn,
+            score_delta: after.current_score() - before.current_score(),
+        });
+    }
+
+    /// Moves recorded so far
+    pub fn num_moves(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Every position in the game, starting with the initial one, so there is one more state
+    /// than there are steps. Errors if the replay doesn't describe a legal game of this size.
+    pub fn states<const N: usize>(&self) -> io::Result<Vec<GameState<N>>> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if self.size != N {
+            return Err(invalid(format!("Replay is for a {0}x{0} board, not {N}x{N}", self.size)));
+        }
+
+        let mut state = GameState::<N>::from_tile_values(&self.initial_board, self.initial_score).map_err(invalid)?;
+        let mut out = vec![state];
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let Spawn { x, y, value } = step.spawn;
+
+            if !state.is_legal_move(step.m) {
+                return Err(invalid(format!("Move {} ({:?}) is illegal", i + 1, step.m)));
+            }
+            let afterstate = state.slide(step.m);
+
+            if x >= N || y >= N || afterstate.get_val(x, y) != 0 || !(value == 2 || value == 4) {
+                return Err(invalid(format!("Move {} has an invalid spawn {:?}", i + 1, step.spawn)));
+            }
+            if afterstate.current_score() - state.current_score() != step.score_delta {
+                return Err(invalid(format!("Move {} has the wrong score delta", i + 1)));
+            }
+
+            state = afterstate.with_tile(x, y, value.trailing_zeros() as u8);
+            out.push(state);
+        }
+
+        Ok(out)
+    }
+
+    /// Write the replay to `path` as JSON
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)
+    }
+
+    /// Read a replay written by `save`
+    pub fn load(path: &str) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let replay = serde_json::from_str(&json)?;
+        Ok(replay)
+    }
+}
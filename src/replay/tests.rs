@@ -0,0 +1,62 @@
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_traits::FullGame;
+use crate::replay::Replay;
+use crate::replay::Spawn;
+
+/// Plays a short game, cycling through the moves and skipping illegal ones
+pub(crate) fn record_game(seed: u64, max_moves: usize) -> (Replay, Vec<GameState<4>>) {
+    let mut rng = RngPlacement::new_from_seed(seed);
+    let mut state = GameState::<4>::new_random(&mut rng);
+
+    let mut replay = Replay::new(&state, Some(seed), "test");
+    let mut states = vec![state];
+
+    for i in 0..max_moves {
+        if state.is_finished() {
+            break;
+        }
+        let m = Move::ALL.into_iter().cycle().skip(i).find(|&m| state.is_legal_move(m)).unwrap();
+        let next = state.apply_move(m, &mut rng).unwrap();
+        replay.record(&state, m, &next);
+        states.push(next);
+        state = next;
+    }
+
+    (replay, states)
+}
+
+#[test]
+fn test_replay_reconstructs_every_state() {
+    let (replay, expected) = record_game(1234, 200);
+
+    assert_eq!(replay.num_moves() + 1, expected.len());
+    assert_eq!(replay.states::<4>().unwrap(), expected);
+}
+
+#[test]
+fn test_replay_json_round_trip() {
+    let (replay, expected) = record_game(99, 50);
+
+    let json = serde_json::to_string(&replay).unwrap();
+    let loaded: Replay = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(loaded.steps, replay.steps);
+    assert_eq!(loaded.states::<4>().unwrap(), expected);
+}
+
+#[test]
+fn test_replay_rejects_bad_data() {
+    let (replay, _) = record_game(7, 20);
+
+    assert!(replay.states::<5>().is_err(), "wrong board size");
+
+    let mut bad_spawn = replay.clone();
+    bad_spawn.steps[3].spawn = Spawn { x: 0, y: 0, value: 8 };
+    assert!(bad_spawn.states::<4>().is_err(), "8 can't spawn");
+
+    let mut bad_score = replay.clone();
+    bad_score.steps[5].score_delta += 2;
+    assert!(bad_score.states::<4>().is_err(), "score delta doesn't match");
+}
@@ -0,0 +1,155 @@
+//! Reward shaping for training. Each `RewardFn` looks at a single transition (state, move, next
+//! state) and scores it; `WeightedReward` sums a bunch of them so we can experiment with which
+//! shaping actually helps learning.
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_traits::FullGame;
+
+#[cfg(test)]
+mod tests;
+
+/// Scores a single transition for the learner
+pub trait RewardFn<const N: usize> {
+    /// Reward for taking `m` in `before`, which resulted in `after` (including the spawned piece)
+    fn reward(&self, before: &GameState<N>, m: Move, after: &GameState<N>) -> f32;
+}
+
+/// The plain score delta; this is what we originally trained on
+pub struct ScoreDelta;
+
+impl<const N: usize> RewardFn<N> for ScoreDelta {
+    fn reward(&self, before: &GameState<N>, _m: Move, after: &GameState<N>) -> f32 {
+        (after.current_score() - before.current_score()) as f32
+    }
+}
+
+/// Log-scaled score delta, so that merging two 1024s isn't worth 500 times merging two 2s
+pub struct LogMergeValue;
+
+impl<const N: usize> RewardFn<N> for LogMergeValue {
+    fn reward(&self, before: &GameState<N>, _m: Move, after: &GameState<N>) -> f32 {
+        let delta = (after.current_score() - before.current_score()) as f32;
+        (1.0 + delta).log2()
+    }
+}
+
+/// One point per empty cell after the move
+pub struct EmptyCellBonus;
+
+impl<const N: usize> RewardFn<N> for EmptyCellBonus {
+    fn reward(&self, _before: &GameState<N>, _m: Move, after: &GameState<N>) -> f32 {
+        after.empty_cells().len() as f32
+    }
+}
+
+/// One point for every move made, i.e. for staying alive
+pub struct SurvivalBonus;
+
+impl<const N: usize> RewardFn<N> for SurvivalBonus {
+    fn reward(&self, _before: &GameState<N>, _m: Move, _after: &GameState<N>) -> f32 {
+        1.0
+    }
+}
+
+/// Minus one if the move ended the game; zero otherwise. Use a positive weight.
+pub struct GameOverPenalty;
+
+impl<const N: usize> RewardFn<N> for GameOverPenalty {
+    fn reward(&self, _before: &GameState<N>, _m: Move, after: &GameState<N>) -> f32 {
+        if after.is_finished() { -1.0 } else { 0.0 }
+    }
+}
+
+/// When the move creates a new highest tile, log2 of that tile (so 11 for reaching 2048)
+pub struct MaxTileMilestone;
+
+impl<const N: usize> RewardFn<N> for MaxTileMilestone {
+    fn reward(&self, before: &GameState<N>, _m: Move, after: &GameState<N>) -> f32 {
+        let new_max = after.highest_tile();
+        if new_max > before.highest_tile() {
+            (new_max as f32).log2()
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Weighted sum of several reward functions
+#[derive(Default)]
+pub struct WeightedReward<const N: usize> {
+    terms: Vec<(f32, Box<dyn RewardFn<N>>)>,
+}
+
+impl<const N: usize> WeightedReward<N> {
+    /// Add `reward_fn`, scaled by `weight`
+    pub fn with_term<R: RewardFn<N> + 'static>(mut self, weight: f32, reward_fn: R) -> Self {
+        self.terms.push((weight, Box::new(reward_fn)));
+        self
+    }
+}
+
+impl<const N: usize> RewardFn<N> for WeightedReward<N> {
+    fn reward(&self, before: &GameState<N>, m: Move, after: &GameState<N>) -> f32 {
+        self.terms.iter().map(|(weight, r)| weight * r.reward(before, m, after)).sum()
+    }
+}
+
+/// Weights for each of the built-in reward functions; zero weights are skipped entirely
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RewardWeights {
+    /// Weight of `ScoreDelta`
+    pub score_delta: f32,
+    /// Weight of `LogMergeValue`
+    pub log_merge: f32,
+    /// Weight of `EmptyCellBonus`
+    pub empty_cells: f32,
+    /// Weight of `SurvivalBonus`
+    pub survival: f32,
+    /// Weight of `GameOverPenalty`
+    pub game_over: f32,
+    /// Weight of `MaxTileMilestone`
+    pub max_tile: f32,
+}
+
+impl Default for RewardWeights {
+    /// Just the score delta, which is the original behavior
+    fn default() -> Self {
+        Self {
+            score_delta: 1.0,
+            log_merge: 0.0,
+            empty_cells: 0.0,
+            survival: 0.0,
+            game_over: 0.0,
+            max_tile: 0.0,
+        }
+    }
+}
+
+impl RewardWeights {
+    /// The weighted sum these weights describe
+    pub fn build<const N: usize>(&self) -> WeightedReward<N> {
+        let mut out = WeightedReward::default();
+
+        if self.score_delta != 0.0 {
+            out = out.with_term(self.score_delta, ScoreDelta);
+        }
+        if self.log_merge != 0.0 {
+            out = out.with_term(self.log_merge, LogMergeValue);
+        }
+        if self.empty_cells != 0.0 {
+            out = out.with_term(self.empty_cells, EmptyCellBonus);
+        }
+        if self.survival != 0.0 {
+            out = out.with_term(self.survival, SurvivalBonus);
+        }
+        if self.game_over != 0.0 {
+            out = out.with_term(self.game_over, GameOverPenalty);
+        }
+        if self.max_tile != 0.0 {
+            out = out.with_term(self.max_tile, MaxTileMilestone);
+        }
+
+        out
+    }
+}
@@ -0,0 +1,61 @@
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::rewards::EmptyCellBonus;
+use crate::rewards::GameOverPenalty;
+use crate::rewards::LogMergeValue;
+use crate::rewards::MaxTileMilestone;
+use crate::rewards::RewardFn;
+use crate::rewards::RewardWeights;
+use crate::rewards::ScoreDelta;
+use crate::rewards::SurvivalBonus;
+
+fn before() -> GameState<2> {
+    GameState::from_grid([[1, 1], [0, 2]], 4)
+}
+
+/// `before` after moving left (two 2s merge into a 4) and spawning a 2 in the top right
+fn after() -> GameState<2> {
+    GameState::from_grid([[2, 1], [2, 0]], 8)
+}
+
+/// A locked board, so the game is over
+fn locked() -> GameState<2> {
+    GameState::from_grid([[1, 2], [2, 1]], 8)
+}
+
+#[test]
+fn test_individual_rewards() {
+    let (b, a) = (before(), after());
+
+    assert_eq!(ScoreDelta.reward(&b, Move::Left, &a), 4.0);
+    assert_eq!(LogMergeValue.reward(&b, Move::Left, &a), 5.0_f32.log2());
+    assert_eq!(EmptyCellBonus.reward(&b, Move::Left, &a), 1.0);
+    assert_eq!(SurvivalBonus.reward(&b, Move::Left, &a), 1.0);
+    assert_eq!(GameOverPenalty.reward(&b, Move::Left, &a), 0.0);
+    assert_eq!(GameOverPenalty.reward(&b, Move::Left, &locked()), -1.0);
+
+    // highest tile stays 4, so no milestone
+    assert_eq!(MaxTileMilestone.reward(&b, Move::Left, &a), 0.0);
+    let milestone = GameState::from_grid([[3, 0], [0, 1]], 12);
+    assert_eq!(MaxTileMilestone.reward(&b, Move::Left, &milestone), 3.0);
+}
+
+#[test]
+fn test_weighted_reward() {
+    let (b, a) = (before(), after());
+
+    let default_reward = RewardWeights::default().build::<2>();
+    assert_eq!(default_reward.reward(&b, Move::Left, &a), 4.0, "default is just the score delta");
+
+    let weights = RewardWeights {
+        score_delta: 0.5,
+        log_merge: 0.0,
+        empty_cells: 2.0,
+        survival: 1.0,
+        game_over: 100.0,
+        max_tile: 0.0,
+    };
+    let reward = weights.build::<2>();
+    assert_eq!(reward.reward(&b, Move::Left, &a), 0.5 * 4.0 + 2.0 * 1.0 + 1.0);
+    assert_eq!(reward.reward(&b, Move::Left, &locked()), 0.5 * 4.0 + 1.0 - 100.0);
+}
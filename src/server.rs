@@ -0,0 +1,330 @@
+//! A TCP server hosting any number of games at once, for notebooks, dashboards and scripted bots.
+//! It speaks line-delimited JSON like the engine protocol (see `protocol`), but every game has an
+//! ID, so many clients can share one server and one client can juggle many games:
+//!
+//! ```text
+//! {"cmd":"create","size":4,"seed":7,"spawn_policy":"classic"}   (every field optional)
+//! {"cmd":"state","id":1}
+//! {"cmd":"move","id":1,"move":"left"}
+//! {"cmd":"undo","id":1}
+//! {"cmd":"close","id":1}
+//! {"cmd":"list"}
+//! {"cmd":"policy","board":[[2,0,0,0],[0,0,0,0],[0,0,4,0],[0,0,0,0]],"score":0}
+//! ```
+//!
+//! Responses are engine protocol responses plus the game's `id`; `list` returns `sessions`, and
+//! `policy` (only if the server was started with a model) returns the model's move probabilities
+//! as `policy`, indexed like `Move::ALL`, and its critic `value`.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+
+#[cfg(feature = "burn")]
+use burn::backend::NdArray;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[cfg(feature = "burn")]
+use crate::agents::Agent;
+#[cfg(feature = "burn")]
+use crate::agents::ModelAgent;
+#[cfg(feature = "burn")]
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+#[cfg(feature = "burn")]
+use crate::model_structs::checkpoint_size;
+use crate::protocol::AnySession;
+use crate::protocol::Response;
+use crate::protocol::move_response;
+use crate::protocol::new_any_session;
+use crate::protocol::write_message;
+#[cfg(feature = "burn")]
+use crate::with_board_size;
+
+#[cfg(test)]
+mod tests;
+
+/// A request, as a client sends it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ServerRequest {
+    /// Start a new game, alongside any others
+    Create {
+        /// Board size; the server's default if left off
+        #[serde(default)]
+        size: Option<usize>,
+        /// Seed for the spawns; random if left off
+        #[serde(default)]
+        seed: Option<u64>,
+        /// How pieces spawn; even if left off
+        #[serde(default)]
+        spawn_policy: Option<SpawnPolicy>,
+    },
+    /// A game's current position
+    State {
+        /// The game, as returned by `create`
+        id: u64,
+    },
+    /// Make a move in a game
+    Move {
+        /// The game, as returned by `create`
+        id: u64,
+        /// Which way to slide
+        #[serde(rename = "move")]
+        m: Move,
+    },
+    /// Take back a game's last move
+    Undo {
+        /// The game, as returned by `create`
+        id: u64,
+    },
+    /// End a game and forget it
+    Close {
+        /// The game, as returned by `create`
+        id: u64,
+    },
+    /// Every game the server is hosting
+    List,
+    /// The model's opinion of a board, which needn't belong to any game
+    Policy {
+        /// Tile values (not exponents), row by row from the top; 0 for empty
+        board: Vec<Vec<u32>>,
+        /// Score so far; 0 if left off
+        #[serde(default)]
+        score: u32,
+    },
+}
+
+/// An engine protocol response, plus what only the server has to say
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerResponse {
+    /// Whether it worked, and the position
+    #[serde(flatten)]
+    pub response: Response,
+    /// The game the response is about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    /// Answer to `list`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<SessionSummary>>,
+    /// The model's probability of each move, indexed like `Move::ALL`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<[f32; 4]>,
+    /// The model's critic value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f32>,
+}
+
+impl From<Response> for ServerResponse {
+    fn from(response: Response) -> Self {
+        Self {
+            response,
+            ..Self::default()
+        }
+    }
+}
+
+/// One line of the `list` response
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    /// The game's ID
+    pub id: u64,
+    /// Board size (N for an NxN board)
+    pub size: usize,
+    /// Score so far
+    pub score: u32,
+    /// Whether there are no legal moves left
+    pub game_over: bool,
+}
+
+/// A model that can judge boards of its own size, whatever that turns out to be
+pub trait PolicyModel: Send {
+    /// Board size the model was trained for
+    fn size(&self) -> usize;
+
+    /// The `policy` response for a board, given as tile values; errors if it's the wrong size
+    fn evaluate(&mut self, board: &[Vec<u32>], score: u32) -> Result<ServerResponse, String>;
+}
+
+#[cfg(feature = "burn")]
+impl<const N: usize> PolicyModel for ModelAgent<N, NdArray> {
+    fn size(&self) -> usize {
+        N
+    }
+
+    fn evaluate(&mut self, board: &[Vec<u32>], score: u32) -> Result<ServerResponse, String> {
+        let state = GameState::<N>::from_tile_values(board, score)?;
+        let eval = Agent::<N>::evaluate(self, &state);
+        Ok(ServerResponse {
+            policy: eval.policy,
+            value: eval.value,
+            ..Response::ok().into()
+        })
+    }
+}
+
+/// Load a `PolicyNet` checkpoint as a `PolicyModel`, at whatever size it was trained for
+#[cfg(feature = "burn")]
+pub fn load_policy_model(path: &str) -> io::Result<Box<dyn PolicyModel>> {
+    let size = checkpoint_size(path)?;
+    with_board_size!(size, N => {
+        let model: Box<dyn PolicyModel> = Box::new(ModelAgent::<N, NdArray>::load(path)?);
+        Ok(model)
+    })
+}
+
+/// Every game on the server, shared between all the connections
+pub struct Server {
+    sessions: Mutex<Sessions>,
+    model: Option<Mutex<Box<dyn PolicyModel>>>,
+    default_size: usize,
+}
+
+struct Sessions {
+    next_id: u64,
+    games: BTreeMap<u64, Box<dyn AnySession>>,
+}
+
+impl Server {
+    /// A server with no games yet. `create` without a size makes a `default_size` game, and
+    /// `policy` needs a `model`.
+    pub fn new(default_size: usize, model: Option<Box<dyn PolicyModel>>) -> Self {
+        Self {
+            sessions: Mutex::new(Sessions {
+                next_id: 1,
+                games: BTreeMap::new(),
+            }),
+            model: model.map(Mutex::new),
+            default_size,
+        }
+    }
+
+    /// Answer one request
+    pub fn handle(&self, request: ServerRequest) -> ServerResponse {
+        // the model has its own lock, so judging boards doesn't hold up the games
+        if let ServerRequest::Policy { board, score } = &request {
+            return self.evaluate(board, *score);
+        }
+
+        let mut sessions = self.sessions.lock().expect("A connection thread panicked");
+
+        let (id, response) = match request {
+            ServerRequest::Create { size, seed, spawn_policy } => {
+                let size = size.unwrap_or(self.default_size);
+                let seed = seed.unwrap_or_else(rand::random);
+                match new_any_session(size, seed, spawn_policy.unwrap_or_default(), "server client") {
+                    Ok(session) => {
+                        let id = sessions.next_id;
+                        sessions.next_id += 1;
+                        let response = Response::with_state(session.view());
+                        sessions.games.insert(id, session);
+                        (id, response)
+                    }
+                    Err(e) => return Response::error(e.to_string()).into(),
+                }
+            }
+            ServerRequest::List => {
+                let summaries = sessions
+                    .games
+                    .iter()
+                    .map(|(&id, session)| {
+                        let view = session.view();
+                        SessionSummary {
+                            id,
+                            size: view.size,
+                            score: view.score,
+                            game_over: view.game_over,
+                        }
+                    })
+                    .collect();
+                return ServerResponse {
+                    sessions: Some(summaries),
+                    ..Response::ok().into()
+                };
+            }
+            ServerRequest::Policy { .. } => unreachable!("handled above"),
+            ServerRequest::Close { id } => match sessions.games.remove(&id) {
+                Some(session) => (id, Response::with_state(session.view())),
+                None => return no_such_game(id),
+            },
+            ServerRequest::State { id } | ServerRequest::Move { id, .. } | ServerRequest::Undo { id } => {
+                let Some(session) = sessions.games.get_mut(&id) else {
+                    return no_such_game(id);
+                };
+                let response = match request {
+                    ServerRequest::Move { m, .. } => move_response(session.as_mut(), m),
+                    ServerRequest::Undo { .. } => match session.undo() {
+                        Ok(()) => Response::with_state(session.view()),
+                        Err(e) => Response::error(e),
+                    },
+                    _ => Response::with_state(session.view()),
+                };
+                (id, response)
+            }
+        };
+
+        ServerResponse {
+            id: Some(id),
+            ..response.into()
+        }
+    }
+
+    fn evaluate(&self, board: &[Vec<u32>], score: u32) -> ServerResponse {
+        let Some(model) = &self.model else {
+            return Response::error("The server was started without a model").into();
+        };
+        let mut model = model.lock().expect("A connection thread panicked");
+        model.evaluate(board, score).unwrap_or_else(|e| Response::error(e).into())
+    }
+}
+
+fn no_such_game(id: u64) -> ServerResponse {
+    Response::error(format!("There is no game with id {id}")).into()
+}
+
+/// Accept connections, serving each one on its own thread. A failed accept (out of file
+/// descriptors, or a client that gave up while queued) is logged and skipped, so the games
+/// already running carry on.
+pub fn serve(listener: TcpListener, server: Arc<Server>) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to accept a connection: {e}");
+                continue;
+            }
+        };
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            // a client hanging up mid-line is its own problem, not the server's
+            let _ = handle_connection(stream, &server);
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, server: &Server) -> io::Result<()> {
+    let mut output = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str(&line) {
+            Ok(request) => server.handle(request),
+            Err(e) => Response::error(format!("Invalid request: {e}")).into(),
+        };
+        write_message(&mut output, &response)?;
+    }
+    output.flush()
+}
@@ -0,0 +1,136 @@
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+
+#[cfg(feature = "burn")]
+use burn::backend::NdArray;
+
+#[cfg(feature = "burn")]
+use crate::agents::ModelAgent;
+use crate::game_structs::Move;
+use crate::server::Server;
+use crate::server::ServerRequest;
+use crate::server::ServerResponse;
+use crate::server::serve;
+
+fn create(server: &Server, seed: u64) -> u64 {
+    let response = server.handle(ServerRequest::Create {
+        size: None,
+        seed: Some(seed),
+        spawn_policy: None,
+    });
+    assert!(response.response.ok);
+    response.id.unwrap()
+}
+
+fn first_legal(response: &ServerResponse) -> Move {
+    response.response.state.as_ref().unwrap().legal_moves[0]
+}
+
+#[test]
+fn test_sessions_are_independent() {
+    let server = Server::new(4, None);
+    let a = create(&server, 1);
+    let b = create(&server, 1);
+    assert_ne!(a, b);
+
+    let state = server.handle(ServerRequest::State { id: a });
+    let moved = server.handle(ServerRequest::Move {
+        id: a,
+        m: first_legal(&state),
+    });
+    assert!(moved.response.ok);
+    assert_eq!(moved.id, Some(a));
+    assert!(moved.response.spawn.is_some());
+
+    // b started from the same seed, but hasn't moved
+    let b_state = server.handle(ServerRequest::State { id: b });
+    assert_eq!(b_state.response.state, state.response.state);
+
+    let list = server.handle(ServerRequest::List).sessions.unwrap();
+    assert_eq!(list.iter().map(|s| s.id).collect::<Vec<_>>(), vec![a, b]);
+
+    assert!(server.handle(ServerRequest::Close { id: a }).response.ok);
+    assert!(!server.handle(ServerRequest::State { id: a }).response.ok);
+    assert_eq!(server.handle(ServerRequest::List).sessions.unwrap().len(), 1);
+}
+
+#[test]
+fn test_undo() {
+    let server = Server::new(4, None);
+    let id = create(&server, 5);
+
+    let before = server.handle(ServerRequest::State { id });
+    let undone = server.handle(ServerRequest::Undo { id });
+    assert!(!undone.response.ok);
+
+    server.handle(ServerRequest::Move {
+        id,
+        m: first_legal(&before),
+    });
+    let undone = server.handle(ServerRequest::Undo { id });
+    assert!(undone.response.ok);
+    assert_eq!(undone.response.state, before.response.state);
+}
+
+#[test]
+#[cfg(feature = "burn")]
+fn test_policy_needs_a_model() {
+    let board = vec![vec![2, 0, 0, 0], vec![0; 4], vec![0, 0, 4, 0], vec![0; 4]];
+
+    let server = Server::new(4, None);
+    let response = server.handle(ServerRequest::Policy {
+        board: board.clone(),
+        score: 0,
+    });
+    assert!(!response.response.ok);
+
+    let server = Server::new(4, Some(Box::new(ModelAgent::<4, NdArray>::untrained())));
+    let response = server.handle(ServerRequest::Policy { board, score: 0 });
+    assert!(response.response.ok);
+    let total: f32 = response.policy.unwrap().iter().sum();
+    assert!((total - 1.0).abs() < 1e-4);
+    assert!(response.value.is_some());
+
+    // the wrong size is an error, not a crash
+    let response = server.handle(ServerRequest::Policy {
+        board: vec![vec![2, 0, 0]; 3],
+        score: 0,
+    });
+    assert!(!response.response.ok);
+}
+
+#[test]
+fn test_over_tcp() {
+    let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || serve(listener, Arc::new(Server::new(4, None))));
+
+    let talk = |lines: &[&str]| -> Vec<ServerResponse> {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        lines
+            .iter()
+            .map(|line| {
+                writeln!(stream, "{line}").unwrap();
+                let mut response = String::new();
+                reader.read_line(&mut response).unwrap();
+                serde_json::from_str(&response).unwrap()
+            })
+            .collect()
+    };
+
+    let first = talk(&[r#"{"cmd":"create","seed":3,"size":5}"#, "nonsense"]);
+    assert_eq!(first[0].response.state.as_ref().unwrap().size, 5);
+    assert!(!first[1].response.ok);
+
+    // a second client sees the first one's game
+    let id = first[0].id.unwrap();
+    let second = talk(&[&format!(r#"{{"cmd":"state","id":{id}}}"#), r#"{"cmd":"list"}"#]);
+    assert_eq!(second[0].response.state, first[0].response.state);
+    assert_eq!(second[1].sessions.as_ref().unwrap().len(), 1);
+}
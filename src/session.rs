@@ -0,0 +1,161 @@
+//! A game in progress: the board, the spawn RNG, the moves so far and how long it's been going.
+//! Sessions can be saved to a JSON file and resumed later with the exact same upcoming spawns.
+
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_structs::RngState;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+use crate::replay::Replay;
+
+#[cfg(test)]
+mod tests;
+
+/// A game being played, with everything needed to save, undo and record it
+pub struct GameSession<const N: usize> {
+    /// The current position
+    pub game: GameState<N>,
+    /// Where the next pieces come from
+    pub rng: RngPlacement,
+    /// Every move made so far, starting from the very first position
+    pub replay: Replay,
+    /// Time spent playing, including any earlier sessions this one was resumed from
+    pub elapsed: Duration,
+    /// Position and RNG state before each move made since this session was created or resumed
+    undo_history: Vec<(GameState<N>, RngState)>,
+}
+
+/// On-disk form of a `GameSession`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedGame {
+    /// Board size (N for an NxN board)
+    pub size: usize,
+    /// Tile values (not exponents) of the current position, row by row
+    pub board: Vec<Vec<u32>>,
+    /// Score of the current position
+    pub score: u32,
+    /// Where the spawn RNG had got to
+    pub rng: RngState,
+    /// Time spent playing so far
+    pub elapsed_secs: f64,
+    /// Every move made so far
+    pub history: Replay,
+}
+
+impl<const N: usize> GameSession<N> {
+    /// A new game, starting from a single random piece
+    pub fn new(seed: u64, spawn_policy: SpawnPolicy, player: impl Into<String>) -> Self {
+        let mut rng = RngPlacement::new_from_seed(seed).with_spawn_policy(spawn_policy);
+        let game = GameState::new_random(&mut rng);
+        Self::starting_at(game, rng, seed, player)
+    }
+
+    /// A new game from a given position instead of a single random piece
+    pub fn from_position(game: GameState<N>, seed: u64, spawn_policy: SpawnPolicy, player: impl Into<String>) -> Self {
+        let rng = RngPlacement::new_from_seed(seed).with_spawn_policy(spawn_policy);
+        let mut session = Self::starting_at(game, rng, seed, player);
+        session.replay.from_position = true;
+        session
+    }
+
+    fn starting_at(game: GameState<N>, rng: RngPlacement, seed: u64, player: impl Into<String>) -> Self {
+        Self {
+            game,
+            rng,
+            replay: Replay::new(&game, Some(seed), player),
+            elapsed: Duration::ZERO,
+            undo_history: Vec::new(),
+        }
+    }
+
+    /// Make a move (placing a new piece and recording it) if it's legal; returns whether it was
+    pub fn apply_move(&mut self, m: Move) -> bool {
+        let rng_before = self.rng.state();
+        match self.game.apply_move(m, &mut self.rng) {
+            Ok(new_state) => {
+                self.undo_history.push((self.game, rng_before));
+                self.replay.record(&self.game, m, &new_state);
+                self.game = new_state;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Take back the last move, if there is one since the session was created or resumed. The
+    /// RNG is rewound as well, so making the same move again brings back the same spawn.
+    pub fn undo(&mut self) -> bool {
+        match self.undo_history.pop() {
+            Some((game, rng)) => {
+                self.game = game;
+                self.rng = RngPlacement::from_state(rng);
+                self.replay.steps.pop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Everything needed to pick the game up later with `from_saved`
+    pub fn to_saved(&self) -> SavedGame {
+        SavedGame {
+            size: N,
+            board: self.game.tile_values(),
+            score: self.game.current_score(),
+            rng: self.rng.state(),
+            elapsed_secs: self.elapsed.as_secs_f64(),
+            history: self.replay.clone(),
+        }
+    }
+
+    /// Pick a saved game back up. Errors if it's for another board size, or if the position
+    /// doesn't match the end of its own history.
+    pub fn from_saved(saved: &SavedGame) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+        if saved.size != N {
+            return Err(invalid(format!("Saved game is for a {0}x{0} board, not {N}x{N}", saved.size)));
+        }
+
+        let game = GameState::<N>::from_tile_values(&saved.board, saved.score).map_err(invalid)?;
+        let history_end = *saved
+            .history
+            .states::<N>()?
+            .last()
+            .expect("A replay always has a starting position");
+        if history_end != game {
+            return Err(invalid("Saved position doesn't match the end of its move history".to_string()));
+        }
+
+        Ok(Self {
+            game,
+            rng: RngPlacement::from_state(saved.rng),
+            replay: saved.history.clone(),
+            elapsed: Duration::try_from_secs_f64(saved.elapsed_secs).map_err(|e| invalid(format!("Invalid elapsed time: {e}")))?,
+            undo_history: Vec::new(),
+        })
+    }
+}
+
+impl SavedGame {
+    /// Write the saved game to `path` as JSON
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(path, json)
+    }
+
+    /// Read a saved game written by `save`
+    pub fn load(path: &str) -> io::Result<Self> {
+        let json = fs::read_to_string(path)?;
+        let saved = serde_json::from_str(&json)?;
+        Ok(saved)
+    }
+}
@@ -0,0 +1,129 @@
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+use crate::session::GameSession;
+use crate::session::SavedGame;
+
+/// Make up to `moves` moves, cycling through the directions and skipping illegal ones
+fn play_some<const N: usize>(session: &mut GameSession<N>, moves: usize) {
+    for i in 0..moves {
+        if session.game.is_finished() {
+            break;
+        }
+        let m = Move::ALL
+            .into_iter()
+            .cycle()
+            .skip(i)
+            .find(|&m| session.game.is_legal_move(m))
+            .unwrap();
+        assert!(session.apply_move(m));
+    }
+}
+
+#[test]
+fn test_resumed_game_gets_identical_spawns() {
+    let mut uninterrupted = GameSession::<4>::new(42, SpawnPolicy::Classic, "human");
+    let mut interrupted = GameSession::<4>::new(42, SpawnPolicy::Classic, "human");
+
+    play_some(&mut uninterrupted, 15);
+    play_some(&mut interrupted, 15);
+    interrupted.elapsed = Duration::from_secs(90);
+
+    // through a file, to cover the serialization too
+    let path = env::temp_dir().join(format!("ai2048-session-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    interrupted.to_saved().save(path).unwrap();
+    let mut resumed = GameSession::<4>::from_saved(&SavedGame::load(path).unwrap()).unwrap();
+    fs::remove_file(path).unwrap();
+
+    assert_eq!(resumed.game, uninterrupted.game);
+    assert_eq!(resumed.elapsed, Duration::from_secs(90));
+
+    for _ in 0..30 {
+        let Some(m) = Move::ALL.into_iter().find(|&m| uninterrupted.game.is_legal_move(m)) else {
+            break;
+        };
+        uninterrupted.apply_move(m);
+        resumed.apply_move(m);
+        assert_eq!(resumed.game, uninterrupted.game);
+    }
+
+    assert_eq!(
+        resumed.replay.steps, uninterrupted.replay.steps,
+        "history carries on from before the save"
+    );
+}
+
+#[test]
+fn test_from_saved_rejects_bad_saves() {
+    let mut session = GameSession::<4>::new(7, SpawnPolicy::Even, "human");
+    play_some(&mut session, 5);
+
+    assert!(GameSession::<5>::from_saved(&session.to_saved()).is_err(), "wrong size");
+
+    let mut tampered = session.to_saved();
+    tampered.score += 4;
+    assert!(GameSession::<4>::from_saved(&tampered).is_err(), "position doesn't match history");
+}
+
+#[test]
+fn test_illegal_moves_are_not_recorded() {
+    let (mut session, illegal) = (0..)
+        .find_map(|seed| {
+            let session = GameSession::<4>::new(seed, SpawnPolicy::Even, "human");
+            let illegal = Move::ALL.into_iter().find(|&m| !session.game.is_legal_move(m))?;
+            Some((session, illegal))
+        })
+        .unwrap();
+    let before = session.game;
+
+    assert!(!session.apply_move(illegal));
+    assert_eq!(session.game, before);
+    assert_eq!(session.replay.num_moves(), 0);
+}
+
+#[test]
+fn test_undo_rewinds_board_history_and_spawns() {
+    let mut session = GameSession::<4>::new(9, SpawnPolicy::Even, "human");
+    assert!(!session.undo());
+
+    play_some(&mut session, 5);
+    let (game, moves) = (session.game, session.replay.num_moves());
+    let m = Move::ALL.into_iter().find(|&m| session.game.is_legal_move(m)).unwrap();
+
+    assert!(session.apply_move(m));
+    let redone = session.game;
+    assert!(session.undo());
+    assert_eq!(session.game, game);
+    assert_eq!(session.replay.num_moves(), moves);
+
+    // the RNG was rewound, so the same move spawns the same piece
+    assert!(session.apply_move(m));
+    assert_eq!(session.game, redone);
+
+    for _ in 0..6 {
+        assert!(session.undo());
+    }
+    assert!(!session.undo());
+    assert_eq!(session.replay.num_moves(), 0);
+}
+
+#[test]
+fn test_games_from_a_position_stay_marked_after_resuming() {
+    assert!(!GameSession::<4>::new(3, SpawnPolicy::Even, "human").replay.from_position);
+
+    // a single tile looks just like a fresh game, but it was still given
+    let mut grid = [[0; 4]; 4];
+    grid[2][1] = 1;
+    let mut session = GameSession::from_position(GameState::<4>::from_grid(grid, 0), 3, SpawnPolicy::Even, "human");
+    assert!(session.replay.from_position);
+
+    play_some(&mut session, 4);
+    let resumed = GameSession::<4>::from_saved(&session.to_saved()).unwrap();
+    assert!(resumed.replay.from_position);
+}
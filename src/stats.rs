@@ -0,0 +1,165 @@
+//! High scores and other statistics from games played by hand, kept in a small JSON file and
+//! tracked separately for each board size and spawn policy.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::game_structs::SpawnPolicy;
+
+#[cfg(test)]
+mod tests;
+
+/// High scores and stats for every board size and spawn policy played so far
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    /// One entry per board size and spawn policy
+    pub boards: Vec<BoardStats>,
+}
+
+/// Everything we know about games on one board size with one spawn policy
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BoardStats {
+    /// Board size (N for an NxN board)
+    pub size: usize,
+    /// How pieces spawned
+    pub spawn_policy: SpawnPolicy,
+    /// Finished games
+    pub games_played: u32,
+    /// Sum of every game's final score
+    pub total_score: u64,
+    /// Highest final score
+    pub best_score: u32,
+    /// Biggest tile any game reached
+    pub best_tile: u32,
+    /// Number of games that ended with each highest tile
+    pub highest_tiles: BTreeMap<u32, u32>,
+}
+
+impl BoardStats {
+    fn new(size: usize, spawn_policy: SpawnPolicy) -> Self {
+        Self {
+            size,
+            spawn_policy,
+            games_played: 0,
+            total_score: 0,
+            best_score: 0,
+            best_tile: 0,
+            highest_tiles: BTreeMap::new(),
+        }
+    }
+
+    /// Mean final score, or 0 before any games
+    pub fn average_score(&self) -> f64 {
+        if self.games_played == 0 {
+            0.0
+        } else {
+            self.total_score as f64 / self.games_played as f64
+        }
+    }
+
+    /// How many games reached each tile (or better), from the smallest highest tile any game
+    /// ended on up to the best tile
+    pub fn tile_reach(&self) -> Vec<(u32, u32)> {
+        let Some((&lowest, _)) = self.highest_tiles.first_key_value() else {
+            return Vec::new();
+        };
+
+        let mut out = Vec::new();
+        let mut tile = lowest;
+        while tile <= self.best_tile {
+            let reached = self.highest_tiles.range(tile..).map(|(_, &count)| count).sum();
+            out.push((tile, reached));
+            tile *= 2;
+        }
+        out
+    }
+
+    /// Human-readable summary: the headline numbers, then how often each tile was reached
+    pub fn summary_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{0}x{0} board, {1} spawns:", self.size, self.spawn_policy),
+            format!(
+                "  {} games played | best score {} | best tile {} | average score {:.0}",
+                self.games_played,
+                self.best_score,
+                self.best_tile,
+                self.average_score()
+            ),
+        ];
+
+        for (tile, reached) in self.tile_reach() {
+            let percent = reached as f64 * 100.0 / self.games_played as f64;
+            lines.push(format!("  reached {tile:>6}: {reached:>5} games ({percent:>5.1}%)"));
+        }
+
+        lines
+    }
+}
+
+impl Stats {
+    /// Where stats are kept unless told otherwise: `$XDG_DATA_HOME/ai2048/stats.json`, falling
+    /// back to `~/.local/share`, or the current directory if there's no home either
+    pub fn default_path() -> PathBuf {
+        let data_dir = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")));
+
+        match data_dir {
+            Some(dir) => dir.join("ai2048").join("stats.json"),
+            None => PathBuf::from("ai2048-stats.json"),
+        }
+    }
+
+    /// Load stats from a file; a missing file just means nothing has been played yet
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the stats to `path` as JSON, creating its directory if need be
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+    }
+
+    /// Stats for one board size and spawn policy, if any games have been played on it
+    pub fn get(&self, size: usize, spawn_policy: SpawnPolicy) -> Option<&BoardStats> {
+        self.boards.iter().find(|b| b.size == size && b.spawn_policy == spawn_policy)
+    }
+
+    /// Add a finished game to the stats for its board, returning the updated stats
+    pub fn record_game(&mut self, size: usize, spawn_policy: SpawnPolicy, score: u32, highest_tile: u32) -> &BoardStats {
+        if self.get(size, spawn_policy).is_none() {
+            self.boards.push(BoardStats::new(size, spawn_policy));
+            self.boards.sort_by_key(|b| (b.size, b.spawn_policy.to_string()));
+        }
+
+        let board = self
+            .boards
+            .iter_mut()
+            .find(|b| b.size == size && b.spawn_policy == spawn_policy)
+            .expect("Board stats were just added");
+        board.games_played += 1;
+        board.total_score += score as u64;
+        board.best_score = board.best_score.max(score);
+        board.best_tile = board.best_tile.max(highest_tile);
+        *board.highest_tiles.entry(highest_tile).or_default() += 1;
+
+        board
+    }
+}
@@ -0,0 +1,64 @@
+use std::env;
+use std::fs;
+
+use crate::game_structs::SpawnPolicy;
+use crate::stats::Stats;
+
+#[test]
+fn test_record_game_updates_board_stats() {
+    let mut stats = Stats::default();
+    stats.record_game(4, SpawnPolicy::Even, 1000, 128);
+    stats.record_game(4, SpawnPolicy::Even, 3000, 256);
+    stats.record_game(4, SpawnPolicy::Even, 2000, 128);
+    stats.record_game(4, SpawnPolicy::Classic, 500, 64);
+    stats.record_game(3, SpawnPolicy::Even, 200, 32);
+
+    let board = stats.get(4, SpawnPolicy::Even).unwrap();
+    assert_eq!(board.games_played, 3);
+    assert_eq!(board.best_score, 3000);
+    assert_eq!(board.best_tile, 256);
+    assert_eq!(board.average_score(), 2000.0);
+    assert_eq!(board.tile_reach(), vec![(128, 3), (256, 1)]);
+
+    let classic = stats.get(4, SpawnPolicy::Classic).unwrap();
+    assert_eq!(classic.games_played, 1);
+    assert_eq!(classic.best_tile, 64);
+
+    stats.record_game(3, SpawnPolicy::Even, 900, 128);
+    let small = stats.get(3, SpawnPolicy::Even).unwrap();
+    assert_eq!(small.tile_reach(), vec![(32, 2), (64, 1), (128, 1)], "tiles in between count too");
+
+    let sizes: Vec<usize> = stats.boards.iter().map(|b| b.size).collect();
+    assert_eq!(sizes, vec![3, 4, 4], "boards are kept sorted");
+    assert!(stats.get(5, SpawnPolicy::Even).is_none());
+}
+
+#[test]
+fn test_stats_file_round_trip() {
+    let dir = env::temp_dir().join(format!("ai2048-stats-{}", std::process::id()));
+    let path = dir.join("nested").join("stats.json");
+
+    let empty = Stats::load(&path).unwrap();
+    assert!(empty.boards.is_empty(), "a missing file means no games yet");
+
+    let mut stats = Stats::default();
+    stats.record_game(5, SpawnPolicy::Classic, 4321, 512);
+    stats.save(&path).unwrap();
+
+    let loaded = Stats::load(&path).unwrap();
+    assert_eq!(loaded.boards, stats.boards);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_summary_lines() {
+    let mut stats = Stats::default();
+    stats.record_game(4, SpawnPolicy::Even, 1000, 128);
+    let summary = stats.record_game(4, SpawnPolicy::Even, 3000, 256).summary_lines();
+
+    assert_eq!(summary[0], "4x4 board, even spawns:");
+    assert!(summary[1].contains("2 games played | best score 3000 | best tile 256 | average score 2000"));
+    assert_eq!(summary[2], "  reached    128:     2 games (100.0%)");
+    assert_eq!(summary[3], "  reached    256:     1 games ( 50.0%)");
+}
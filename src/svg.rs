@@ -0,0 +1,202 @@
+//! Standalone SVG pictures of boards, for sharing positions in reviews and reports. A single
+//! board gets the score above it and optionally an arrow for a move; a replay can be drawn as
+//! one contact sheet or as a numbered frame per position.
+
+use std::fmt::Write;
+
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_traits::FullGame;
+use crate::palette;
+use crate::palette::Rgb;
+use crate::replay::Replay;
+
+#[cfg(test)]
+mod tests;
+
+/// Side of a cell, and the gap around cells
+const CELL: u32 = 100;
+const GAP: u32 = 12;
+
+/// Room above a board for its caption
+const HEADER: u32 = 56;
+
+/// Space between boards on a contact sheet
+const SHEET_MARGIN: u32 = 24;
+
+const BACKGROUND: Rgb = (250, 248, 239);
+const ARROW: Rgb = (60, 58, 50);
+
+const FONT: &str = "'Clear Sans', 'Helvetica Neue', Arial, sans-serif";
+
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Width (and height) of the board itself, not counting its caption
+fn board_side<const N: usize>() -> u32 {
+    N as u32 * CELL + (N as u32 + 1) * GAP
+}
+
+/// Smaller digits for longer numbers, so every tile value fits its cell
+fn font_size(value: u32) -> u32 {
+    match value {
+        0..=99 => 52,
+        100..=999 => 44,
+        1000..=9999 => 34,
+        10000..=99999 => 28,
+        _ => 22,
+    }
+}
+
+/// One board with a caption above it, its top-left corner at (x, y)
+fn board_group<const N: usize>(out: &mut String, game: &GameState<N>, caption: &str, arrow: Option<Move>, x: u32, y: u32) {
+    let side = board_side::<N>();
+    let _ = writeln!(out, r#"<g transform="translate({x},{y})">"#);
+    let _ = writeln!(
+        out,
+        r#"<text x="0" y="{}" font-family="{FONT}" font-size="20" font-weight="bold" fill="{}">{}</text>"#,
+        HEADER - 16,
+        hex(palette::DARK_TEXT),
+        escape(caption)
+    );
+
+    let _ = writeln!(out, r#"<g transform="translate(0,{HEADER})">"#);
+    let _ = writeln!(
+        out,
+        r#"<rect width="{side}" height="{side}" rx="8" fill="{}"/>"#,
+        hex(palette::GRID)
+    );
+    for (row, values) in game.tile_values().iter().enumerate() {
+        for (col, &value) in values.iter().enumerate() {
+            let exponent = if value == 0 { 0 } else { value.trailing_zeros() as u8 };
+            let cx = GAP + col as u32 * (CELL + GAP);
+            let cy = GAP + row as u32 * (CELL + GAP);
+            let _ = writeln!(
+                out,
+                r#"<rect x="{cx}" y="{cy}" width="{CELL}" height="{CELL}" rx="5" fill="{}"/>"#,
+                hex(palette::tile_background(exponent))
+            );
+            if value != 0 {
+                let _ = writeln!(
+                    out,
+                    r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central" font-family="{FONT}" font-size="{}" font-weight="bold" fill="{}">{value}</text>"#,
+                    cx + CELL / 2,
+                    cy + CELL / 2,
+                    font_size(value),
+                    hex(palette::tile_text(exponent))
+                );
+            }
+        }
+    }
+    if let Some(m) = arrow {
+        draw_arrow(out, m, side);
+    }
+    let _ = writeln!(out, "</g>");
+    let _ = writeln!(out, "</g>");
+}
+
+/// A translucent arrow across the middle of a board, pointing the way `m` slides the tiles
+fn draw_arrow(out: &mut String, m: Move, side: u32) {
+    let degrees = match m {
+        Move::Right => 0,
+        Move::Down => 90,
+        Move::Left => 180,
+        Move::Up => 270,
+    };
+    let c = side as f32 / 2.0;
+    let len = side as f32 * 0.3;
+    let shaft = side as f32 * 0.05;
+    let head = side as f32 * 0.12;
+    // pointing right, centered on the origin, then rotated into place
+    let points = [
+        (-len, -shaft),
+        (len - head, -shaft),
+        (len - head, -head),
+        (len, 0.0),
+        (len - head, head),
+        (len - head, shaft),
+        (-len, shaft),
+    ]
+    .map(|(px, py)| format!("{px:.1},{py:.1}"))
+    .join(" ");
+    let _ = writeln!(
+        out,
+        r#"<polygon class="arrow" points="{points}" transform="translate({c:.1},{c:.1}) rotate({degrees})" fill="{}" fill-opacity="0.6"/>"#,
+        hex(ARROW)
+    );
+}
+
+fn document(width: u32, height: u32, body: &str) -> String {
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">
+<rect width="{width}" height="{height}" fill="{}"/>
+{body}</svg>
+"#,
+        hex(BACKGROUND)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn single_board<const N: usize>(game: &GameState<N>, caption: &str, arrow: Option<Move>) -> String {
+    let mut body = String::new();
+    board_group(&mut body, game, caption, arrow, GAP, GAP);
+    document(board_side::<N>() + 2 * GAP, board_side::<N>() + HEADER + 2 * GAP, &body)
+}
+
+/// A single board with its score, and an arrow for `arrow` if given
+pub fn render_board<const N: usize>(game: &GameState<N>, arrow: Option<Move>) -> String {
+    single_board(game, &format!("Score: {}", game.current_score()), arrow)
+}
+
+/// Caption for the position after `moves` moves of a replay, and the move made from it
+fn replay_caption(replay: &Replay, moves: usize, score: u32) -> (String, Option<Move>) {
+    let next = replay.steps.get(moves).map(|step| step.m);
+    let caption = match next {
+        Some(m) => format!("Move {moves}, score {score}, then {m:?}"),
+        None => format!("Move {moves}, score {score}, end"),
+    };
+    (caption, next)
+}
+
+/// Every position of a replay on one sheet, `columns` to a row, each with an arrow for the move
+/// that was made from it
+pub fn render_contact_sheet<const N: usize>(replay: &Replay, states: &[GameState<N>], columns: usize) -> String {
+    let columns = columns.clamp(1, states.len().max(1)) as u32;
+    let rows = (states.len() as u32).div_ceil(columns);
+    let cell_w = board_side::<N>() + SHEET_MARGIN;
+    let cell_h = board_side::<N>() + HEADER + SHEET_MARGIN;
+
+    let mut body = String::new();
+    for (i, state) in states.iter().enumerate() {
+        let (caption, next) = replay_caption(replay, i, state.current_score());
+        let x = SHEET_MARGIN + (i as u32 % columns) * cell_w;
+        let y = SHEET_MARGIN + (i as u32 / columns) * cell_h;
+        board_group(&mut body, state, &caption, next, x, y);
+    }
+
+    document(SHEET_MARGIN + columns * cell_w, SHEET_MARGIN + rows * cell_h, &body)
+}
+
+/// One standalone picture per position of a replay, in order
+pub fn render_frames<const N: usize>(replay: &Replay, states: &[GameState<N>]) -> Vec<String> {
+    states
+        .iter()
+        .enumerate()
+        .map(|(i, state)| {
+            let (caption, next) = replay_caption(replay, i, state.current_score());
+            single_board(state, &caption, next)
+        })
+        .collect()
+}
+
+/// File names for numbered frames: `game.svg` becomes `game-00.svg`, `game-01.svg`, ... (with
+/// as many digits as the last frame number needs)
+pub fn frame_paths(out: &str, num_frames: usize) -> Vec<String> {
+    let stem = out.strip_suffix(".svg").unwrap_or(out);
+    let digits = num_frames.saturating_sub(1).max(1).ilog10() as usize + 1;
+    (0..num_frames).map(|i| format!("{stem}-{i:0digits$}.svg")).collect()
+}
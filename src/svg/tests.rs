@@ -0,0 +1,63 @@
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_traits::FullGame;
+use crate::replay::tests::record_game;
+use crate::svg::frame_paths;
+use crate::svg::render_board;
+use crate::svg::render_contact_sheet;
+use crate::svg::render_frames;
+
+#[test]
+fn test_render_board() {
+    #[rustfmt::skip]
+    let game = GameState::from_grid(
+        [
+            [1, 0, 0],
+            [0, 11, 0],
+            [0, 0, 17],
+        ],
+        1234,
+    );
+
+    let svg = render_board(&game, None);
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"372\" height=\"428\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(">Score: 1234</text>"));
+    for value in ["2", "2048", "131072"] {
+        assert!(svg.contains(&format!(">{value}</text>")), "missing tile {value}");
+    }
+    // the web game's colors for 2 and 2048, and the empty cells
+    assert!(svg.contains("fill=\"#eee4da\""));
+    assert!(svg.contains("fill=\"#edc22e\""));
+    assert_eq!(svg.matches("fill=\"#cdc1b4\"").count(), 6);
+    assert!(!svg.contains("class=\"arrow\""));
+
+    let svg = render_board(&game, Some(Move::Up));
+    assert!(svg.contains("class=\"arrow\""));
+    assert!(svg.contains("rotate(270)"));
+}
+
+#[test]
+fn test_render_replay() {
+    let (replay, states) = record_game(4, 3);
+    assert!(!states.last().unwrap().is_finished());
+
+    let sheet = render_contact_sheet(&replay, &states, 2);
+    assert_eq!(sheet.matches("<svg").count(), 1);
+    assert_eq!(sheet.matches("class=\"arrow\"").count(), states.len() - 1);
+    assert!(sheet.contains(&format!("Move 0, score 0, then {:?}", replay.steps[0].m)));
+    assert!(sheet.contains(&format!("Move {}, score", states.len() - 1)));
+
+    let frames = render_frames(&replay, &states);
+    assert_eq!(frames.len(), states.len());
+    assert!(frames.iter().all(|frame| frame.starts_with("<svg")));
+    assert!(!frames.last().unwrap().contains("class=\"arrow\""));
+}
+
+#[test]
+fn test_frame_paths() {
+    assert_eq!(frame_paths("game.svg", 3), vec!["game-0.svg", "game-1.svg", "game-2.svg"]);
+    let paths = frame_paths("out/game", 120);
+    assert_eq!(paths[0], "out/game-000.svg");
+    assert_eq!(paths[119], "out/game-119.svg");
+}
@@ -1,3 +1,8 @@
+//! Training the `PolicyNet` by self-play. Each batch plays some games, then takes a few optimizer
+//! steps on what happened in them. By default that's REINFORCE on the shaped rewards; see
+//! `TrainingMode` for the alternative.
+
+use std::io;
 use std::time::Duration;
 use std::time::Instant;
 
@@ -11,21 +16,30 @@ use burn::prelude::*;
 use burn::tensor::Tensor;
 use burn::tensor::activation::log_softmax;
 
+use crate::env::Env;
+use crate::env::OneHotEncoder;
 use crate::game_structs::GameState;
 use crate::game_structs::Move;
-use crate::game_structs::RngPlacement;
 use crate::game_traits::FullGame;
 use crate::model_structs::InnerModel;
 use crate::model_structs::PolicyNet;
 use crate::model_traits::Model;
 use crate::model_traits::MoveResult;
+use crate::rewards::RewardFn;
+use crate::rewards::RewardWeights;
+
+mod alphazero;
+
+pub use alphazero::AlphaZeroConfig;
+pub use alphazero::n_step_returns;
 
+/// One self-play move, as the REINFORCE learner sees it
 pub struct Reward<const N: usize, B: Backend> {
     /// Game state that was acted on, in tensor form
     state: Tensor<B, 1>,
     /// Chosen move from the model
     output: Move,
-    /// Including discounted future rewards, then normalized
+    /// Shaped reward (see `RewardFn`), including discounted future rewards
     reward: f32,
     /// Local penalties; not discounted
     penalty: f32,
@@ -40,6 +54,7 @@ fn mean_stddev(xs: &[f32]) -> (f32, f32) {
 }
 
 // Used to prevent divide by zero when normalizing
+#[allow(dead_code)]
 const EPSILON: f32 = 0.0001;
 
 type AD = Autodiff<NdArray<f32>>;
@@ -71,15 +86,144 @@ fn batchify<const N: usize>(batch: &[Reward<N, AD>], device: &<AD as Backend>::D
     BatchifyResult { x, returns, actions }
 }
 
-pub fn train<const N: usize>(
+/// Hyperparameters for a training run
+#[derive(Clone, Debug)]
+pub struct TrainingConfig {
+    /// Stop starting new batches after this many seconds
+    pub max_time_sec: usize,
+    /// Step size for the Adam optimizer
+    pub learning_rate: f64,
+    /// Self-play games between each round of learning
+    pub games_per_batch: usize,
+    /// Optimizer steps taken on each batch
+    pub learning_steps_per_batch: usize,
+    /// How much each later reward counts, per move, toward the return. REINFORCE only; see
+    /// `AlphaZeroConfig`
+    pub discount_factor: f32,
+    /// Weight decay
+    pub l2_reg: f32,
+    /// Weights for the shaped reward; see `rewards`. REINFORCE only, like `discount_factor`
+    pub reward_weights: RewardWeights,
+    /// Where self-play moves come from; see `TrainingMode`
+    pub mode: TrainingMode,
+}
+
+/// Where self-play moves come from, and what the model learns from them
+#[derive(Clone, Debug, Default)]
+pub enum TrainingMode {
+    /// Moves straight from the policy, learned from with REINFORCE
+    #[default]
+    Reinforce,
+    /// Moves from a tree search guided by the model, which learns to match the search; see
+    /// `AlphaZeroConfig`
+    AlphaZero(AlphaZeroConfig),
+}
+
+/// Everything we measured about a single batch
+#[derive(Clone, Debug)]
+pub struct BatchStats {
+    /// Counts up from 1
+    pub batch_idx: usize,
+    /// Games played in the batch; fewer than asked for if training was stopped
+    pub num_games: usize,
+    /// Total moves made across all games in the batch
+    pub num_steps: usize,
+    /// Mean final score of the batch's games
+    pub mean_score: f32,
+    /// Standard deviation of the final scores
+    pub stddev_score: f32,
+    /// Highest tile reached in any game of the batch
+    pub max_tile: u32,
+    /// Illegal first choices per move, on average
+    pub avg_illegal_moves: f32,
+    /// Mean advantage from the last learning step, to see how the critic is doing
+    pub adv_mean: f32,
+    /// Policy loss from the last learning step
+    pub actor_loss: f32,
+    /// Value loss from the last learning step
+    pub critic_loss: f32,
+    /// Mean entropy of the policy over the batch's positions, from the last learning step
+    pub entropy: f32,
+    /// Time spent playing the batch's games
+    pub play_secs: f64,
+    /// Time spent on the optimizer steps
+    pub learning_secs: f64,
+    /// Time for the whole batch
+    pub batch_secs: f64,
+    /// Time since training started
+    pub total_secs: f64,
+}
+
+/// Hooks for watching a training run
+pub trait TrainingObserver<const N: usize> {
+    /// Called after every move of every self-play game
+    fn on_move(&mut self, _state: &GameState<N>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called after each batch has been played and learned from
+    fn on_batch(&mut self, stats: &BatchStats) -> io::Result<()>;
+
+    /// Checked between games; returning true ends training early
+    fn should_stop(&mut self) -> io::Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Prints a few lines of diagnostics per batch
+pub struct PrintObserver;
+
+impl<const N: usize> TrainingObserver<N> for PrintObserver {
+    fn on_batch(&mut self, stats: &BatchStats) -> io::Result<()> {
+        let BatchStats {
+            batch_idx,
+            adv_mean,
+            avg_illegal_moves,
+            mean_score,
+            stddev_score,
+            play_secs,
+            learning_secs,
+            batch_secs,
+            total_secs,
+            ..
+        } = stats;
+
+        println!(
+            "batch {batch_idx:>5} | adv_mean={adv_mean:0.3} | avg_illegal_moves={avg_illegal_moves:0.3} | mean_score={mean_score:.2} | score_std={stddev_score:.2}"
+        );
+        println!(
+            "    Timing: Play time {:0.3} sec | learning {:0.3} sec | Total (batch) {:0.3} sec | Total (all) {:0.3} sec",
+            play_secs, learning_secs, batch_secs, total_secs
+        );
+        println!("    Batch finished at {}", chrono::Local::now());
+
+        Ok(())
+    }
+}
+
+/// Train until `max_time_sec` runs out or the observer asks us to stop
+pub fn train<const N: usize, O: TrainingObserver<N> + ?Sized>(
     model: &mut PolicyNet<N, AD>,
-    max_time_sec: usize,
-    lr: f64,
-    games_per_batch: usize,
-    learning_steps_per_batch: usize,
-    discount_factor: f32,
-    l2_reg: f32,
-) {
+    config: &TrainingConfig,
+    observer: &mut O,
+) -> io::Result<()> {
+    if let TrainingMode::AlphaZero(alphazero) = &config.mode {
+        return alphazero::train(model, config, alphazero, observer);
+    }
+
+    let TrainingConfig {
+        max_time_sec,
+        learning_rate: lr,
+        games_per_batch,
+        learning_steps_per_batch,
+        discount_factor,
+        l2_reg,
+        reward_weights,
+        mode: _,
+    } = config.clone();
+
+    let mut env = Env::new(OneHotEncoder, reward_weights.build::<N>());
+
     let device = <AD as Backend>::Device::default();
     let mut opt = AdamConfig::new()
         .with_weight_decay(Some(WeightDecayConfig::new(l2_reg)))
@@ -89,7 +233,7 @@ pub fn train<const N: usize>(
     let end_time = start_time + Duration::from_secs(max_time_sec as u64);
 
     let mut batch_idx = 0;
-    while Instant::now() < end_time {
+    'training: while Instant::now() < end_time {
         batch_idx += 1;
 
         let batch_start_time = Instant::now();
@@ -98,12 +242,18 @@ pub fn train<const N: usize>(
         let mut batch: Vec<Reward<N, AD>> = Vec::new();
 
         let mut final_scores: Vec<f32> = Vec::new();
+        let mut max_tile = 0;
 
         let play_start_time = Instant::now();
 
         for _ in 0..games_per_batch {
-            let (game_results, final_score) = simulate_one_game(model, &device, discount_factor);
-            final_scores.push(final_score as f32);
+            if observer.should_stop()? {
+                break 'training;
+            }
+
+            let (game_results, final_state) = simulate_one_game(model, &mut env, &device, discount_factor, observer)?;
+            final_scores.push(final_state.current_score() as f32);
+            max_tile = max_tile.max(final_state.highest_tile());
             batch.extend(game_results);
         }
 
@@ -124,8 +274,9 @@ pub fn train<const N: usize>(
         } = batchify(&batch, &device);
         // let normalized_returns = normalize(non_normalized_returns.clone());
 
-        // mean advantage -- only used for debug output, to see how the critic is doing
+        // only used for diagnostics, to see how the critic is doing
         let mut adv_mean = 0.0;
+        let mut losses = (0.0, 0.0, 0.0);
 
         let learning_start = Instant::now();
 
@@ -151,6 +302,7 @@ pub fn train<const N: usize>(
             let critic_loss: Tensor<AD, 1> = advantages.clone().powf_scalar(2.0).mean();
             let entropy = -((log_props.clone().exp() * log_props).sum_dim(1)).mean();
 
+            losses = (scalar(&actor_loss), scalar(&critic_loss), scalar(&entropy));
             let loss: Tensor<AD, 1> = actor_loss.clone() + 0.25 * critic_loss - 0.01 * entropy;
 
             // 8) Backprop + step
@@ -164,18 +316,35 @@ pub fn train<const N: usize>(
         let batch_elapsed = batch_start_time.elapsed().as_secs_f64();
         let total_elapsed = start_time.elapsed().as_secs_f64();
 
-        // (Optional) diagnostics
-        println!(
-            "batch {batch_idx:>5} | adv_mean={adv_mean:0.3} | avg_illegal_moves={avg_illegal_moves:0.3} | mean_score={mean_score:.2} | score_std={stddev_score:.2}"
-        );
-        println!(
-            "    Timing: Play time {:0.3} sec | learning {:0.3} sec | Total (batch) {:0.3} sec | Total (all) {:0.3} sec",
-            play_elapsed, learning_elapsed, batch_elapsed, total_elapsed
-        );
-        println!("    Batch finished at {}", chrono::Local::now());
+        let (actor_loss, critic_loss, entropy) = losses;
+        observer.on_batch(&BatchStats {
+            batch_idx,
+            num_games: final_scores.len(),
+            num_steps: batch.len(),
+            mean_score,
+            stddev_score,
+            max_tile,
+            avg_illegal_moves,
+            adv_mean,
+            actor_loss,
+            critic_loss,
+            entropy,
+            play_secs: play_elapsed,
+            learning_secs: learning_elapsed,
+            batch_secs: batch_elapsed,
+            total_secs: total_elapsed,
+        })?;
     }
+
+    Ok(())
 }
 
+/// Pull a single value out of a one-element tensor
+fn scalar(tensor: &Tensor<AD, 1>) -> f32 {
+    tensor.clone().into_data().into_vec::<f32>().unwrap()[0]
+}
+
+#[allow(dead_code)]
 fn normalize<B: Backend>(tensor: Tensor<B, 1>) -> Tensor<B, 1> {
     let (var, mean) = tensor.clone().var_mean(0);
 
@@ -185,40 +354,43 @@ fn normalize<B: Backend>(tensor: Tensor<B, 1>) -> Tensor<B, 1> {
 /// How much problem it is if you give an illegal move
 pub const ILLEGAL_MOVE_PENALTY: f32 = -1000.0;
 
-/// Returns rewards for a single game with the given model. These are discounted (that is, credit
-/// is sent backwards across time) but not normalized, which should be done per batch.
-fn simulate_one_game<const N: usize, B: Backend>(
+/// Returns rewards for a single game with the given model, along with the final state. These are
+/// discounted (that is, credit is sent backwards across time) but not normalized, which should be
+/// done per batch.
+fn simulate_one_game<const N: usize, B: Backend, R: RewardFn<N>, O: TrainingObserver<N> + ?Sized>(
     model: &PolicyNet<N, B>,
+    env: &mut Env<N, OneHotEncoder, R>,
     device: &B::Device,
     discount_factor: f32,
-) -> (Vec<Reward<N, B>>, u32) {
-    let mut prng = RngPlacement::new();
-    let mut game_state = GameState::new_random(&mut prng);
+    observer: &mut O,
+) -> io::Result<(Vec<Reward<N, B>>, GameState<N>)> {
+    let mut observation = env.reset(None);
 
     let mut rewards: Vec<Reward<N, B>> = Vec::new();
 
-    while !game_state.is_finished() {
-        let input_tensor = model.input_to_tensor(&game_state, device);
+    loop {
+        let input_tensor = Tensor::<B, 1>::from_data(observation.features.as_slice(), device);
         let (actor_logits, _critic_value) = model.get_output_tensor(input_tensor.clone());
         let MoveResult {
             next_move,
             num_illegal_choices,
-        } = model.get_move_from_output(&game_state, actor_logits.clone());
-
-        let new_game_state = game_state
-            .apply_move(next_move, &mut prng)
-            .expect("Should only generate valid moves");
+        } = model.get_move_from_output(env.state(), actor_logits.clone());
 
-        let reward = (new_game_state.current_score() - game_state.current_score()) as f32;
+        let step = env.step(next_move);
+        assert!(!step.info.illegal_move, "Should only generate valid moves");
 
         rewards.push(Reward {
             state: input_tensor,
             output: next_move,
-            reward,
+            reward: step.reward,
             penalty: (num_illegal_choices as f32) * ILLEGAL_MOVE_PENALTY,
         });
 
-        game_state = new_game_state;
+        observer.on_move(env.state())?;
+        if step.terminated || step.truncated {
+            break;
+        }
+        observation = step.observation;
     }
 
     // Apply discounted rewards
@@ -229,7 +401,5 @@ fn simulate_one_game<const N: usize, B: Backend>(
         reward.reward = running_reward;
     }
 
-    let final_score = game_state.current_score();
-
-    (rewards, final_score)
+    Ok((rewards, *env.state()))
 }
@@ -0,0 +1,315 @@
+//! AlphaZero-style training. Self-play moves come from a tree search guided by the model being
+//! trained; the actor learns to match the search's visit counts (cross-entropy), and the critic
+//! learns the returns that followed each move, optionally bootstrapped from its own later values.
+//!
+//! The search values a critic leaf at the current score plus the critic's value, so the critic has
+//! to estimate the points still to come. Its returns are therefore the undiscounted score deltas,
+//! whatever the training config's discount and reward weights say; those only shape REINFORCE.
+//! They're learned in units of `CRITIC_SCALE` points, so that on big boards, where thousands of
+//! points are still to come, the critic's loss doesn't drown out the actor's.
+
+use std::io;
+use std::time::Duration;
+use std::time::Instant;
+
+use burn::backend::NdArray;
+use burn::backend::ndarray::NdArrayDevice;
+use burn::module::AutodiffModule;
+use burn::optim::AdamConfig;
+use burn::optim::GradientsParams;
+use burn::optim::Optimizer;
+use burn::optim::decay::WeightDecayConfig;
+use burn::prelude::*;
+use burn::tensor::activation::log_softmax;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use super::AD;
+use super::BatchStats;
+use super::TrainingConfig;
+use super::TrainingObserver;
+use super::mean_stddev;
+use super::scalar;
+use crate::agents::Agent;
+use crate::agents::CRITIC_SCALE;
+use crate::agents::MctsAgent;
+use crate::agents::MctsConfig;
+use crate::agents::ModelAgent;
+use crate::env::Env;
+use crate::env::ObservationEncoder;
+use crate::env::OneHotEncoder;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_traits::FullGame;
+use crate::model_structs::InnerModel;
+use crate::model_structs::PolicyNet;
+use crate::model_traits::Model;
+use crate::rewards::RewardFn;
+use crate::rewards::ScoreDelta;
+
+#[cfg(test)]
+mod tests;
+
+/// Settings for AlphaZero-style training
+#[derive(Clone, Debug)]
+pub struct AlphaZeroConfig {
+    /// Search used to pick self-play moves. The model being trained supplies its priors and
+    /// critic values, so it doesn't need a checkpoint of its own.
+    pub mcts: MctsConfig,
+    /// Bootstrap the critic's targets from its own value this many moves ahead, rather than
+    /// using every reward to the end of the game
+    pub bootstrap_steps: Option<usize>,
+    /// Moves at the start of each game picked in proportion to the visit counts, rather than
+    /// the most visited, so self-play doesn't keep repeating itself
+    pub sample_moves: usize,
+}
+
+/// One self-play move, as the learner sees it
+struct SearchStep {
+    features: Vec<f32>,
+    /// Fraction of the search's visits that went to each move; the actor's target
+    visits: [f32; 4],
+    reward: f32,
+    /// The critic's value for the position, if bootstrapping
+    value: f32,
+}
+
+/// Critic targets for one game: each step's reward plus the discounted rewards after it. With
+/// `steps`, only that many rewards are summed, and the value of the position that many moves
+/// later stands in for the rest. The game ended after the last step, so nothing follows it.
+pub fn n_step_returns(rewards: &[f32], values: &[f32], discount: f32, steps: Option<usize>) -> Vec<f32> {
+    let Some(steps) = steps else {
+        let mut running = 0.0;
+        let mut out: Vec<f32> = rewards
+            .iter()
+            .rev()
+            .map(|&reward| {
+                running = running * discount + reward;
+                running
+            })
+            .collect();
+        out.reverse();
+        return out;
+    };
+
+    (0..rewards.len())
+        .map(|t| {
+            let end = (t + steps).min(rewards.len());
+            let mut total = 0.0;
+            let mut weight = 1.0;
+            for &reward in &rewards[t..end] {
+                total += weight * reward;
+                weight *= discount;
+            }
+            if end < rewards.len() {
+                total += weight * values[end];
+            }
+            total
+        })
+        .collect()
+}
+
+/// Critic targets for one game's steps: the points still to come after each one, in units of
+/// `CRITIC_SCALE`, bootstrapped if asked from the critic's own (already scaled) values
+fn critic_targets(steps: &[SearchStep], bootstrap_steps: Option<usize>) -> Vec<f32> {
+    let rewards: Vec<f32> = steps.iter().map(|s| s.reward / CRITIC_SCALE).collect();
+    let values: Vec<f32> = steps.iter().map(|s| s.value).collect();
+    n_step_returns(&rewards, &values, 1.0, bootstrap_steps)
+}
+
+/// Losses from one learning step
+#[derive(Copy, Clone, Debug, Default)]
+struct StepLosses {
+    actor: f32,
+    critic: f32,
+    entropy: f32,
+    /// Mean difference between the returns and the critic's values, before the step
+    adv_mean: f32,
+}
+
+/// One optimizer step: cross-entropy toward the visit distributions `targets` for the actor,
+/// and squared error toward `returns` for the critic
+fn learning_step<const N: usize, O: Optimizer<InnerModel<AD>, AD>>(
+    model: &mut PolicyNet<N, AD>,
+    opt: &mut O,
+    learning_rate: f64,
+    x: &Tensor<AD, 2>,
+    targets: &Tensor<AD, 2>,
+    returns: &Tensor<AD, 1>,
+) -> StepLosses {
+    let (actor_logits, critic_value) = model.get_output_tensor(x.clone());
+    let log_probs = log_softmax(actor_logits, 1); // [B, 4]
+
+    let actor_loss: Tensor<AD, 1> = -(targets.clone() * log_probs.clone()).sum_dim(1).mean();
+    let advantages = returns.clone() - critic_value.squeeze::<1>(1);
+    let adv_mean = scalar(&advantages.clone().mean());
+    let critic_loss: Tensor<AD, 1> = advantages.powf_scalar(2.0).mean();
+    let entropy = -((log_probs.clone().exp() * log_probs).sum_dim(1)).mean();
+
+    let losses = StepLosses {
+        actor: scalar(&actor_loss),
+        critic: scalar(&critic_loss),
+        entropy: scalar(&entropy),
+        adv_mean,
+    };
+    let loss: Tensor<AD, 1> = actor_loss + 0.25 * critic_loss;
+
+    let grads = loss.backward();
+    let grads = GradientsParams::from_grads::<AD, _>(grads, &model.inner);
+    model.inner = opt.step(learning_rate, model.inner.clone(), grads);
+    losses
+}
+
+/// The model as it is now, on the plain (non-autodiff) backend, to guide the search
+fn snapshot<const N: usize>(model: &PolicyNet<N, AD>) -> ModelAgent<N, NdArray> {
+    let inner: InnerModel<NdArray> = model.inner.valid();
+    ModelAgent::new(PolicyNet { inner }, NdArrayDevice::default(), "training snapshot")
+}
+
+/// Pick a move in proportion to `weights`
+fn sample_move(weights: [f32; 4], rng: &mut StdRng) -> Move {
+    let total: f32 = weights.iter().sum();
+    let mut pick = rng.random_range(0.0..total);
+    for m in Move::ALL {
+        if pick < weights[m.to_idx()] {
+            return m;
+        }
+        pick -= weights[m.to_idx()];
+    }
+    Move::ALL
+        .into_iter()
+        .rfind(|m| weights[m.to_idx()] > 0.0)
+        .expect("Some move was visited")
+}
+
+/// Play one game with moves from the search, recording its targets along the way
+fn play_one_game<const N: usize, R: RewardFn<N>, O: TrainingObserver<N> + ?Sized>(
+    search: &mut MctsAgent<N>,
+    mut critic: Option<&mut ModelAgent<N, NdArray>>,
+    env: &mut Env<N, OneHotEncoder, R>,
+    sample_moves: usize,
+    rng: &mut StdRng,
+    observer: &mut O,
+) -> io::Result<(Vec<SearchStep>, GameState<N>)> {
+    let mut observation = env.reset(None);
+    let mut steps = Vec::new();
+
+    loop {
+        let state = *env.state();
+        let result = search.search(&state);
+        let visits = result.visit_distribution();
+        let next_move = if steps.len() < sample_moves {
+            sample_move(visits, rng)
+        } else {
+            result.best_move()
+        };
+        let value = critic.as_mut().and_then(|critic| critic.evaluate(&state).value).unwrap_or(0.0);
+
+        let step = env.step(next_move);
+        steps.push(SearchStep {
+            features: observation.features,
+            visits,
+            reward: step.reward,
+            value,
+        });
+
+        observer.on_move(env.state())?;
+        if step.terminated || step.truncated {
+            break;
+        }
+        observation = step.observation;
+    }
+
+    Ok((steps, *env.state()))
+}
+
+/// Train until `max_time_sec` runs out or the observer asks us to stop; see `train`
+pub(super) fn train<const N: usize, O: TrainingObserver<N> + ?Sized>(
+    model: &mut PolicyNet<N, AD>,
+    config: &TrainingConfig,
+    alphazero: &AlphaZeroConfig,
+    observer: &mut O,
+) -> io::Result<()> {
+    // the critic learns points still to come, to match how the search values leaves
+    let mut env = Env::new(OneHotEncoder, ScoreDelta);
+    let num_features = ObservationEncoder::<N>::num_features(&OneHotEncoder);
+    let mut rng = StdRng::from_os_rng();
+
+    let device = <AD as Backend>::Device::default();
+    let mut opt = AdamConfig::new()
+        .with_weight_decay(Some(WeightDecayConfig::new(config.l2_reg)))
+        .init::<AD, InnerModel<AD>>();
+
+    let start_time = Instant::now();
+    let end_time = start_time + Duration::from_secs(config.max_time_sec as u64);
+
+    let mut batch_idx = 0;
+    'training: while Instant::now() < end_time {
+        batch_idx += 1;
+        let batch_start_time = Instant::now();
+
+        // 1) Self-play, searching with the model as it stood at the start of the batch
+        let mut search = MctsAgent::new(alphazero.mcts, Some(Box::new(snapshot(model))));
+        let mut critic = alphazero.bootstrap_steps.map(|_| snapshot(model));
+
+        let mut batch: Vec<SearchStep> = Vec::new();
+        let mut returns: Vec<f32> = Vec::new();
+        let mut final_scores: Vec<f32> = Vec::new();
+        let mut max_tile = 0;
+
+        let play_start_time = Instant::now();
+        for _ in 0..config.games_per_batch {
+            if observer.should_stop()? {
+                break 'training;
+            }
+
+            let (steps, final_state) = play_one_game(&mut search, critic.as_mut(), &mut env, alphazero.sample_moves, &mut rng, observer)?;
+            final_scores.push(final_state.current_score() as f32);
+            max_tile = max_tile.max(final_state.highest_tile());
+
+            returns.extend(critic_targets(&steps, alphazero.bootstrap_steps));
+            batch.extend(steps);
+        }
+        let play_elapsed = play_start_time.elapsed().as_secs_f64();
+
+        let (mean_score, stddev_score) = mean_stddev(&final_scores);
+
+        // 2) Batchify: positions, visit distributions and returns
+        let b = batch.len();
+        let features: Vec<f32> = batch.iter().flat_map(|s| s.features.iter().copied()).collect();
+        let targets: Vec<f32> = batch.iter().flat_map(|s| s.visits).collect();
+        let x = Tensor::<AD, 2>::from_data(TensorData::new(features, [b, num_features]), &device);
+        let targets = Tensor::<AD, 2>::from_data(TensorData::new(targets, [b, 4]), &device);
+        let returns = Tensor::<AD, 1>::from_floats(returns.as_slice(), &device);
+
+        // 3) Cross-entropy toward the visit distribution, and regression toward the returns
+        let learning_start = Instant::now();
+        let mut losses = StepLosses::default();
+        for _ in 0..config.learning_steps_per_batch {
+            losses = learning_step(model, &mut opt, config.learning_rate, &x, &targets, &returns);
+        }
+        let learning_elapsed = learning_start.elapsed().as_secs_f64();
+
+        observer.on_batch(&BatchStats {
+            batch_idx,
+            num_games: final_scores.len(),
+            num_steps: b,
+            mean_score,
+            stddev_score,
+            max_tile,
+            // the search only ever plays legal moves
+            avg_illegal_moves: 0.0,
+            adv_mean: losses.adv_mean,
+            actor_loss: losses.actor,
+            critic_loss: losses.critic,
+            entropy: losses.entropy,
+            play_secs: play_elapsed,
+            learning_secs: learning_elapsed,
+            batch_secs: batch_start_time.elapsed().as_secs_f64(),
+            total_secs: start_time.elapsed().as_secs_f64(),
+        })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+use std::io;
+
+use burn::optim::AdamConfig;
+use burn::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use super::critic_targets;
+use super::learning_step;
+use super::play_one_game;
+use super::snapshot;
+use super::train;
+use crate::agents::Agent;
+use crate::agents::CRITIC_SCALE;
+use crate::agents::MctsAgent;
+use crate::agents::MctsConfig;
+use crate::env::Env;
+use crate::env::OneHotEncoder;
+use crate::env::one_hot_channels;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_traits::FullGame;
+use crate::model_structs::InnerModel;
+use crate::model_structs::PolicyNet;
+use crate::model_structs::PolicyNetConfig;
+use crate::rewards::RewardWeights;
+use crate::rewards::ScoreDelta;
+use crate::training::AD;
+use crate::training::AlphaZeroConfig;
+use crate::training::BatchStats;
+use crate::training::TrainingConfig;
+use crate::training::TrainingMode;
+use crate::training::TrainingObserver;
+use crate::training::n_step_returns;
+
+#[test]
+fn test_full_returns() {
+    let returns = n_step_returns(&[1.0, 2.0, 4.0], &[0.0; 3], 0.5, None);
+    assert_eq!(returns, vec![1.0 + 0.5 * (2.0 + 0.5 * 4.0), 2.0 + 0.5 * 4.0, 4.0]);
+}
+
+#[test]
+fn test_bootstrapped_returns() {
+    let rewards = [1.0, 2.0, 4.0, 8.0];
+    let values = [100.0, 200.0, 300.0, 400.0];
+
+    // two rewards, then the critic's value two moves later; the last steps run out of game
+    let returns = n_step_returns(&rewards, &values, 0.5, Some(2));
+    assert_eq!(
+        returns,
+        vec![1.0 + 0.5 * 2.0 + 0.25 * 300.0, 2.0 + 0.5 * 4.0 + 0.25 * 400.0, 4.0 + 0.5 * 8.0, 8.0]
+    );
+
+    // enough steps to reach the end is the same as not bootstrapping at all
+    assert_eq!(
+        n_step_returns(&rewards, &values, 0.5, Some(10)),
+        n_step_returns(&rewards, &values, 0.5, None)
+    );
+}
+
+/// Keeps the first batch's stats, and stops training after it
+#[derive(Default)]
+struct FirstBatch {
+    stats: Option<BatchStats>,
+}
+
+impl<const N: usize> TrainingObserver<N> for FirstBatch {
+    fn on_batch(&mut self, stats: &BatchStats) -> io::Result<()> {
+        self.stats.get_or_insert_with(|| stats.clone());
+        Ok(())
+    }
+
+    fn should_stop(&mut self) -> io::Result<bool> {
+        Ok(self.stats.is_some())
+    }
+}
+
+#[test]
+fn test_one_batch_moves_the_policy_toward_the_search() {
+    // a single simulation visits the first legal move, so the actor's target is always that move
+    let alphazero = AlphaZeroConfig {
+        mcts: MctsConfig {
+            simulations: 1,
+            ..Default::default()
+        },
+        bootstrap_steps: None,
+        sample_moves: 0,
+    };
+    let config = TrainingConfig {
+        max_time_sec: 60,
+        learning_rate: 0.003,
+        games_per_batch: 2,
+        learning_steps_per_batch: 20,
+        discount_factor: 0.99,
+        l2_reg: 0.0,
+        reward_weights: RewardWeights::default(),
+        mode: TrainingMode::AlphaZero(alphazero.clone()),
+    };
+
+    let mut model: PolicyNet<3, AD> = PolicyNetConfig::new().init(&Default::default());
+    let mut rng = RngPlacement::new_from_seed(6);
+    let states: Vec<GameState<3>> = (0..20).map(|_| GameState::new_random(&mut rng)).collect();
+    let mean_target_prob = |model: &PolicyNet<3, AD>| {
+        let mut agent = snapshot(model);
+        let total: f32 = states
+            .iter()
+            .map(|state| {
+                let target = Move::ALL.into_iter().find(|&m| state.is_legal_move(m)).unwrap();
+                agent.evaluate(state).policy.unwrap()[target.to_idx()]
+            })
+            .sum();
+        total / states.len() as f32
+    };
+
+    let before = mean_target_prob(&model);
+    let mut observer = FirstBatch::default();
+    train(&mut model, &config, &alphazero, &mut observer).unwrap();
+    let after = mean_target_prob(&model);
+
+    let stats = observer.stats.unwrap();
+    assert_eq!(stats.num_games, 2);
+    for loss in [stats.actor_loss, stats.critic_loss, stats.entropy] {
+        assert!(loss.is_finite(), "{stats:?}");
+    }
+    assert!(after > before, "target move went from {before} to {after}");
+}
+
+#[test]
+fn test_actor_still_learns_on_4x4() {
+    // hundreds or thousands of points are still to come early in a 4x4 game; learning them mustn't
+    // drown out learning the visit distributions
+    let mut search = MctsAgent::<4>::new(
+        MctsConfig {
+            simulations: 1,
+            ..Default::default()
+        },
+        None,
+    )
+    .with_seed(1);
+    let mut env = Env::new(OneHotEncoder, ScoreDelta);
+    let mut rng = StdRng::seed_from_u64(1);
+    let (steps, final_state) = play_one_game(&mut search, None, &mut env, 0, &mut rng, &mut FirstBatch::default()).unwrap();
+
+    let returns = critic_targets(&steps, None);
+    assert!((returns[0] - final_state.current_score() as f32 / CRITIC_SCALE).abs() < 1e-3);
+
+    let device = Default::default();
+    let features: Vec<f32> = steps.iter().flat_map(|s| s.features.iter().copied()).collect();
+    let targets: Vec<f32> = steps.iter().flat_map(|s| s.visits).collect();
+    let x = Tensor::<AD, 2>::from_data(TensorData::new(features, [steps.len(), 4 * 4 * one_hot_channels(4)]), &device);
+    let targets = Tensor::<AD, 2>::from_data(TensorData::new(targets, [steps.len(), 4]), &device);
+    let returns = Tensor::<AD, 1>::from_floats(returns.as_slice(), &device);
+
+    let mut model: PolicyNet<4, AD> = PolicyNetConfig::new().init(&device);
+    let mut opt = AdamConfig::new().init::<AD, InnerModel<AD>>();
+    let first = learning_step(&mut model, &mut opt, 0.003, &x, &targets, &returns);
+    let mut last = first;
+    for _ in 0..30 {
+        last = learning_step(&mut model, &mut opt, 0.003, &x, &targets, &returns);
+    }
+
+    assert!(last.actor.is_finite() && last.critic.is_finite(), "{last:?}");
+    assert!(
+        last.actor < 0.5 * first.actor,
+        "actor loss went from {} to {}",
+        first.actor,
+        last.actor
+    );
+}
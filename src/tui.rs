@@ -5,170 +5,544 @@
 use std::io;
 use std::io::Write;
 use std::time::Duration;
+use std::time::Instant;
 
-use burn::prelude::Backend;
 use crossterm::cursor;
 use crossterm::event::Event;
 use crossterm::event::KeyCode;
 use crossterm::execute;
-use crossterm::style::Color;
+use crossterm::queue;
 use crossterm::style::Print;
-use crossterm::style::ResetColor;
-use crossterm::style::SetBackgroundColor;
-use crossterm::style::SetForegroundColor;
 use crossterm::terminal;
 
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
 use crate::game_structs::GameState;
 use crate::game_structs::Move;
-use crate::game_structs::RngPlacement;
 use crate::game_traits::FullGame;
-use crate::model_structs::PolicyNet;
-use crate::model_traits::Model;
-use crate::model_traits::MoveResult;
+use crate::session::GameSession;
 
-pub fn render<const N: usize>(game: &GameState<N>) -> io::Result<()> {
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+mod cast;
+mod compare;
+#[cfg(feature = "burn")]
+mod dashboard;
+mod frame;
+mod overlay;
+mod replay_viewer;
+mod theme;
+
+#[cfg(test)]
+mod tests;
+
+pub use cast::CastTiming;
+pub use cast::write_cast;
+pub use compare::compare;
+#[cfg(feature = "burn")]
+pub use dashboard::TrainingDashboard;
+use frame::Frame;
+use frame::Style;
+use overlay::draw_overlay;
+pub use replay_viewer::view_replay;
+use theme::Theme;
+pub use theme::ThemeChoice;
+use theme::current_theme;
+pub use theme::set_theme;
+
+/// Raw mode on the alternate screen, for views that take over the terminal. Dropping it puts the
+/// terminal back, so an agent failing (or panicking) partway through doesn't leave it unusable.
+struct AlternateScreen {
+    left: bool,
+}
+
+impl AlternateScreen {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(Self { left: false })
+    }
+
+    /// Go back to the normal screen, staying in raw mode until dropped
+    fn leave(&mut self) -> io::Result<()> {
+        self.left = true;
+        execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen)
+    }
+}
+
+impl Drop for AlternateScreen {
+    fn drop(&mut self) {
+        if !self.left {
+            let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        }
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Tiles up to this many digits all get the same cell width, so the board doesn't keep
+/// changing size early in the game
+const MIN_TILE_DIGITS: u16 = 4;
+
+/// Width of a single rendered cell, including its borders, for a board whose largest tile is
+/// `highest_tile`
+fn cell_width(highest_tile: u32) -> u16 {
+    let digits = highest_tile.max(1).ilog10() as u16 + 1;
+    digits.max(MIN_TILE_DIGITS) + 3
+}
+
+/// Width of a whole rendered board
+fn board_width<const N: usize>(game: &GameState<N>) -> u16 {
+    cell_width(game.highest_tile()) * N as u16
+}
+
+/// Draw the board (just the grid, no score) with its top-left corner at (col, row)
+fn draw_board<const N: usize>(frame: &mut Frame, game: &GameState<N>, col: u16, row: u16) {
+    draw_board_themed(frame, game, current_theme(), col, row);
+}
+
+fn draw_board_themed<const N: usize>(frame: &mut Frame, game: &GameState<N>, theme: Theme, col: u16, row: u16) {
+    let cell_width = cell_width(game.highest_tile());
+    let inner = cell_width as usize - 2;
 
     for y in 0..N {
         for x in 0..N {
             let val = game.get_val(x, y);
-            let val = if val == 0 { 0 } else { 1_u32 << val };
-            let color = match val {
-                0 => Color::DarkGrey,
-                2 => Color::Grey,
-                4 => Color::White,
-                8 => Color::Yellow,
-                16 => Color::DarkYellow,
-                32 => Color::Magenta,
-                64 => Color::Red,
-                128 => Color::Blue,
-                256 => Color::Cyan,
-                512 => Color::Green,
-                _ => Color::White,
-            };
-            execute!(
-                stdout,
-                SetBackgroundColor(Color::Black),
-                SetForegroundColor(color),
-                Print(format!("|{:>6}|", if val == 0 { ".".to_string() } else { val.to_string() })),
-                ResetColor,
-            )?;
+            let label = if val == 0 { ".".to_string() } else { (1_u32 << val).to_string() };
+            let text = format!("|{label:>inner$}|");
+            frame.put_str(col + x as u16 * cell_width, row + y as u16, &text, theme.tile_style(val));
         }
-        execute!(stdout, Print("\r\n"))?;
     }
+}
+
+/// The board with the score underneath
+fn game_frame<const N: usize>(game: &GameState<N>) -> Frame {
+    let mut frame = Frame::new();
+    draw_board(&mut frame, game, 0, 0);
+    frame.push_line("");
+    frame.push_line(format!("Score: {}", game.current_score()));
+    frame
+}
+
+/// Clear the screen and draw the frame, leaving the cursor on the line below it
+fn present(frame: &Frame) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    queue_screen(&mut stdout, frame)?;
+    stdout.flush()
+}
+
+/// Queue everything `present` draws, without flushing
+fn queue_screen<W: Write>(out: &mut W, frame: &Frame) -> io::Result<()> {
+    queue!(out, terminal::Clear(terminal::ClearType::All))?;
+    frame.write_to(out, 0, 0)?;
+    queue!(out, cursor::MoveTo(0, frame.height()))
+}
 
-    execute!(stdout, Print(format!("\nScore: {}\n", game.current_score())))?;
-    stdout.flush()?;
+/// Tiny bar chart of the last `width` values, scaled between their min and max
+fn sparkline(values: &[f32], width: usize) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let values = &values[values.len().saturating_sub(width)..];
+    let lo = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let hi = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+    values
+        .iter()
+        .map(|&v| {
+            if !v.is_finite() {
+                ' '
+            } else if hi > lo {
+                BARS[(((v - lo) / (hi - lo)) * (BARS.len() - 1) as f32).round() as usize]
+            } else {
+                BARS[0]
+            }
+        })
+        .collect()
+}
+
+/// Draw `game` at the top of the screen
+pub fn render<const N: usize>(game: &GameState<N>) -> io::Result<()> {
+    present(&game_frame(game))
+}
+
+/// What the hint agent thinks of each move in this position
+fn hint_lines<const N: usize>(game: &GameState<N>, hint_agent: Option<&mut Box<dyn Agent<N>>>) -> Vec<String> {
+    let Some(agent) = hint_agent else {
+        return vec!["No hint agent loaded; pass --agent to get hints".to_string()];
+    };
+
+    let eval = agent.evaluate(game);
+
+    // The agent's favorite legal move: by expected score if it has them, otherwise by policy
+    let preference = |m: Move| match (&eval.expected_scores, &eval.policy) {
+        (Some(scores), _) => scores[m.to_idx()],
+        (None, Some(policy)) => Some(policy[m.to_idx()]),
+        (None, None) => None,
+    };
+    let best = Move::ALL
+        .into_iter()
+        .filter(|&m| game.is_legal_move(m))
+        .filter_map(|m| preference(m).map(|p| (m, p)))
+        .reduce(|a, b| if b.1 > a.1 { b } else { a })
+        .map(|(m, _)| m);
+
+    let mut lines = vec![format!("Hints from {}:", agent.name())];
+    for m in Move::ALL {
+        let mut line = format!("  {:<6}", format!("{m:?}"));
+        if let Some(policy) = &eval.policy {
+            line += &format!("  policy {:>5.1}%", policy[m.to_idx()] * 100.0);
+        }
+        if let Some(scores) = &eval.expected_scores {
+            match scores[m.to_idx()] {
+                Some(score) => line += &format!("  expected score {score:>9.1}"),
+                None => line += &format!("  expected score {:>9}", "-"),
+            }
+        }
+        if !game.is_legal_move(m) {
+            line += "  (illegal)";
+        } else if best == Some(m) {
+            line += "  <- suggested";
+        }
+        lines.push(line);
+    }
+    if let Some(value) = eval.value {
+        lines.push(format!("  Critic value: {value:.2}"));
+    }
+
+    lines
+}
+
+/// Play state that isn't part of the game itself
+struct PlayStatus {
+    show_hints: bool,
+    /// Feedback from the last save, if any
+    message: Option<String>,
+}
+
+/// Minutes and seconds, with hours if it comes to that
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes}:{secs:02}")
+    }
+}
+
+fn render_play<const N: usize>(
+    session: &GameSession<N>,
+    status: &PlayStatus,
+    hint_agent: Option<&mut Box<dyn Agent<N>>>,
+) -> io::Result<()> {
+    let mut frame = game_frame(&session.game);
+    frame.push_line(format!("Time: {}", format_elapsed(session.elapsed)));
+
+    if status.show_hints {
+        frame.push_line("");
+        for line in hint_lines(&session.game, hint_agent) {
+            frame.push_line(line);
+        }
+    }
+    if let Some(message) = &status.message {
+        frame.push_line("");
+        frame.push_line(message);
+    }
 
-    Ok(())
+    present(&frame)
 }
 
-pub fn play<const N: usize>(seed: Option<u64>) -> io::Result<()> {
-    let mut rng = match seed {
-        Some(seed) => RngPlacement::new_from_seed(seed),
-        None => RngPlacement::new(),
+/// Play interactively, starting from `session` (a new game or a resumed one). If a hint agent
+/// is given, pressing h toggles a panel showing what it thinks of each move. Pressing S saves
+/// the game to `save_path`. When the game is over, `game_over` is asked for summary lines (e.g.
+/// the updated stats), which are shown with the final board until a key is pressed. Returns the
+/// session as it stood when the game ended or was quit.
+pub fn play<const N: usize>(
+    mut session: GameSession<N>,
+    save_path: &str,
+    mut hint_agent: Option<Box<dyn Agent<N>>>,
+    game_over: impl FnOnce(&GameSession<N>) -> io::Result<Vec<String>>,
+) -> io::Result<GameSession<N>> {
+    let mut status = PlayStatus {
+        show_hints: false,
+        message: None,
     };
-    let mut game = GameState::<4>::new_random(&mut rng);
+    let started = Instant::now();
+    let elapsed_before = session.elapsed;
 
     // prepare terminal
-    terminal::enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let mut screen = AlternateScreen::enter()?;
 
-    render(&game)?;
+    render_play(&session, &status, hint_agent.as_mut())?;
 
-    loop {
+    while !session.game.is_finished() {
         if crossterm::event::poll(Duration::from_millis(100))?
             && let Event::Key(key) = crossterm::event::read()?
         {
+            session.elapsed = elapsed_before + started.elapsed();
+
             let mv = match key.code {
                 KeyCode::Char('q') => break, // quit
+                KeyCode::Char('h') => {
+                    status.show_hints = !status.show_hints;
+                    None
+                }
+                KeyCode::Char('S') => {
+                    status.message = Some(match session.to_saved().save(save_path) {
+                        Ok(()) => format!("Saved to {save_path}; continue later with play --resume {save_path}"),
+                        Err(e) => format!("Could not save to {save_path}: {e}"),
+                    });
+                    None
+                }
                 KeyCode::Up | KeyCode::Char('w') => Some(Move::Up),
                 KeyCode::Down | KeyCode::Char('s') => Some(Move::Down),
                 KeyCode::Left | KeyCode::Char('a') => Some(Move::Left),
                 KeyCode::Right | KeyCode::Char('d') => Some(Move::Right),
-                _ => None,
+                _ => continue,
             };
 
             if let Some(mv) = mv {
-                if let Ok(new_state) = game.apply_move(mv, &mut rng) {
-                    game = new_state;
-                }
-                render(&game)?;
-                if game.is_finished() {
-                    execute!(stdout, Print("Game over!"))?;
-                    break;
-                }
+                session.apply_move(mv);
             }
+            render_play(&session, &status, hint_agent.as_mut())?;
         }
     }
+    session.elapsed = elapsed_before + started.elapsed();
+
+    let summary = if session.game.is_finished() {
+        let summary = game_over(&session)?;
+        render_game_over(&session, &summary)?;
+        wait_for_key()?;
+        summary
+    } else {
+        vec![]
+    };
 
     // cleanup
-    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    screen.leave()?;
 
-    render(&game)?;
+    let game = &session.game;
+    render(game)?;
 
-    println!("\r\nGame over! Final score: {}\n", game.current_score());
+    if game.is_finished() {
+        println!("\r\nGame over! Final score: {}\n", game.current_score());
+    } else {
+        println!("\r\nStopped early. Score: {}\n", game.current_score());
+    }
     println!("\r\n          Highest tile: {}\n", game.highest_tile());
+    println!("\r\n          Time played: {}\n", format_elapsed(session.elapsed));
+    for line in &summary {
+        println!("{line}\r");
+    }
 
-    terminal::disable_raw_mode()?;
+    drop(screen);
 
-    Ok(())
+    Ok(session)
 }
 
-// TODO: allow the user to hit Q to stop it early
-// TODO: some kind of display that it's a CPU autoplaying
-pub fn simulate<const N: usize, B: Backend>(seed: Option<u64>, model: &PolicyNet<N, B>, device: &B::Device) -> io::Result<()> {
-    let mut rng = match seed {
-        Some(seed) => RngPlacement::new_from_seed(seed),
-        None => RngPlacement::new(),
-    };
-    let mut game = GameState::new_random(&mut rng);
+/// The final board, with the game's summary under it
+fn render_game_over<const N: usize>(session: &GameSession<N>, summary: &[String]) -> io::Result<()> {
+    let mut frame = game_frame(&session.game);
+    frame.push_line(format!("Time: {}", format_elapsed(session.elapsed)));
+    frame.push_line("");
+    frame.push_line(format!("Game over! Final score: {}", session.game.current_score()));
+    if !summary.is_empty() {
+        frame.push_line("");
+        for line in summary {
+            frame.push_line(line);
+        }
+    }
+    frame.push_line("");
+    frame.push_line("Press any key to exit");
 
-    // prepare terminal
-    terminal::enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    present(&frame)
+}
+
+/// Block until a key is pressed
+fn wait_for_key() -> io::Result<()> {
+    loop {
+        if let Event::Key(_) = crossterm::event::read()? {
+            return Ok(());
+        }
+    }
+}
 
-    let mut wrong_moves = vec![];
+/// Delay between moves when autoplay starts
+const DEFAULT_STEP_DELAY: Duration = Duration::from_millis(150);
+const MIN_STEP_DELAY: Duration = Duration::from_millis(10);
+const MAX_STEP_DELAY: Duration = Duration::from_millis(2560);
 
-    render(&game)?;
+/// Autoplay state that isn't part of the game itself
+struct AutoplayStatus<const N: usize> {
+    paused: bool,
+    step_delay: Duration,
+    wrong_moves: Vec<Move>,
+    show_overlay: bool,
+    /// The last position the agent was asked about; kept while the overlay is hidden, so showing
+    /// it again on the same position doesn't search it again
+    evaluated: Option<GameState<N>>,
+    /// The agent's opinion of `evaluated`; None if that game was already over
+    evaluation: Option<MoveEvaluation>,
+    /// The move the agent picked while forming that opinion, to be played next
+    planned_move: Option<Move>,
+    /// The agent's value estimate for each position it has evaluated so far
+    values: Vec<f32>,
+}
 
-    loop {
-        std::thread::sleep(Duration::from_millis(150));
+impl<const N: usize> AutoplayStatus<N> {
+    /// Ask the agent about the current position, if anyone is looking and it hasn't been asked
+    /// already. Its move comes from the same search and is kept for the next step, so the
+    /// position is only searched once.
+    fn evaluate<A: Agent<N> + ?Sized>(&mut self, game: &GameState<N>, agent: &mut A) -> io::Result<()> {
+        if !self.show_overlay || self.evaluated == Some(*game) {
+            return Ok(());
+        }
+
+        self.evaluated = Some(*game);
+        self.evaluation = None;
+        self.planned_move = None;
+        // nothing to search on a finished board
+        if game.is_finished() {
+            return Ok(());
+        }
 
-        let MoveResult { next_move, .. } = model.get_next_move(&game, device);
+        let (m, eval) = agent.choose_with_evaluation(game)?;
+        self.planned_move = Some(m);
+        if let Some(value) = eval.value {
+            self.values.push(value);
+        }
+        self.evaluation = Some(eval);
+        Ok(())
+    }
 
-        if let Ok(new_state) = game.apply_move(next_move, &mut rng) {
-            game = new_state;
-            wrong_moves.clear();
+    /// What to draw in the overlay for `game`, if it's showing
+    fn overlay(&self, game: &GameState<N>) -> Option<&MoveEvaluation> {
+        if self.show_overlay && self.evaluated == Some(*game) {
+            self.evaluation.as_ref()
         } else {
-            wrong_moves.push(next_move);
+            None
         }
+    }
+}
 
-        render(&game)?;
+fn render_autoplay<const N: usize>(game: &GameState<N>, agent_name: &str, status: &AutoplayStatus<N>) -> io::Result<()> {
+    let mut frame = game_frame(game);
 
-        if !wrong_moves.is_empty() {
-            let message = format!("\r\nWrong moves: {wrong_moves:?}");
-            execute!(stdout, Print(message.as_str()))?;
+    if let Some(eval) = status.overlay(game) {
+        let legal = Move::ALL.map(|m| game.is_legal_move(m));
+        draw_overlay(&mut frame, eval, legal, &status.values, current_theme(), board_width(game) + 4, 0);
+    }
+
+    let state = if status.paused { "PAUSED" } else { "running" };
+    frame.push_line("");
+    frame.push_line(format!("CPU playing: {agent_name}"));
+    frame.push_line(format!("{state} | {}ms per move", status.step_delay.as_millis()));
+    frame.push_line("[space] pause/resume  [n] step while paused  [+/-] speed  [o] overlay  [q] quit");
+
+    if !status.wrong_moves.is_empty() {
+        frame.push_line("");
+        frame.push_line(format!("Wrong moves: {:?}", status.wrong_moves));
+    }
+
+    present(&frame)
+}
+
+/// Let the agent play the session's game in the terminal, with an overlay of its move
+/// probabilities and value estimates. Space pauses, n steps while paused, +/- change the speed,
+/// o toggles the overlay, and q quits early. Returns the session as it stood at the end.
+pub fn simulate<const N: usize, A: Agent<N> + ?Sized>(mut session: GameSession<N>, agent: &mut A) -> io::Result<GameSession<N>> {
+    let agent_name = agent.name();
+
+    // prepare terminal
+    let mut screen = AlternateScreen::enter()?;
+    let mut stdout = io::stdout();
+
+    let mut status = AutoplayStatus {
+        paused: false,
+        step_delay: DEFAULT_STEP_DELAY,
+        wrong_moves: vec![],
+        show_overlay: true,
+        evaluated: None,
+        evaluation: None,
+        planned_move: None,
+        values: vec![],
+    };
+    let mut last_step = Instant::now();
+    let mut quit_early = false;
+
+    status.evaluate(&session.game, agent)?;
+
+    render_autoplay(&session.game, &agent_name, &status)?;
+
+    while !session.game.is_finished() {
+        let mut take_step = !status.paused && last_step.elapsed() >= status.step_delay;
+
+        if !take_step {
+            let timeout = if status.paused {
+                Duration::from_millis(100)
+            } else {
+                status.step_delay.saturating_sub(last_step.elapsed())
+            };
+
+            if crossterm::event::poll(timeout)?
+                && let Event::Key(key) = crossterm::event::read()?
+            {
+                match key.code {
+                    KeyCode::Char('q') => {
+                        quit_early = true;
+                        break;
+                    }
+                    KeyCode::Char(' ') => status.paused = !status.paused,
+                    KeyCode::Char('n') if status.paused => take_step = true,
+                    KeyCode::Char('+') | KeyCode::Char('=') => status.step_delay = (status.step_delay / 2).max(MIN_STEP_DELAY),
+                    KeyCode::Char('-') => status.step_delay = (status.step_delay * 2).min(MAX_STEP_DELAY),
+                    KeyCode::Char('o') => {
+                        status.show_overlay = !status.show_overlay;
+                        status.evaluate(&session.game, agent)?;
+                    }
+                    _ => continue,
+                }
+
+                render_autoplay(&session.game, &agent_name, &status)?;
+            }
+
+            if !take_step {
+                continue;
+            }
         }
 
-        if game.is_finished() {
-            execute!(stdout, Print("\r\nGame over!"))?;
-            break;
+        last_step = Instant::now();
+
+        let next_move = match status.planned_move.take() {
+            Some(m) => m,
+            None => agent.choose_move(&session.game)?,
+        };
+
+        if session.apply_move(next_move) {
+            status.wrong_moves.clear();
+            status.evaluate(&session.game, agent)?;
+        } else {
+            status.wrong_moves.push(next_move);
         }
+
+        render_autoplay(&session.game, &agent_name, &status)?;
+    }
+
+    if !quit_early {
+        execute!(stdout, Print("Game over!"))?;
     }
 
     // cleanup
-    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    screen.leave()?;
 
-    render(&game)?;
+    let game = &session.game;
+    render(game)?;
 
-    println!("\r\nGame over! Final score: {}\n", game.current_score());
+    if quit_early {
+        println!("\r\nStopped early. Score: {}\n", game.current_score());
+    } else {
+        println!("\r\nGame over! Final score: {}\n", game.current_score());
+    }
     println!("\r\n          Highest tile: {}\n", game.highest_tile());
 
-    terminal::disable_raw_mode()?;
+    drop(screen);
 
-    Ok(())
+    Ok(session)
 }
@@ -0,0 +1,94 @@
+//! Replays as asciinema v2 recordings (`.cast` files), drawn exactly as `render` draws them, so a
+//! game can be shared as a terminal recording without capturing the screen.
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+
+use crossterm::cursor;
+use crossterm::queue;
+use serde::Serialize;
+
+use super::game_frame;
+use super::queue_screen;
+use crate::game_structs::GameState;
+use crate::replay::Replay;
+
+#[cfg(test)]
+mod tests;
+
+/// How long each position stays on screen in a recording
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CastTiming {
+    /// Time between one move and the next
+    pub move_delay: Duration,
+    /// How long the final position is held before the recording ends
+    pub end_delay: Duration,
+}
+
+impl Default for CastTiming {
+    fn default() -> Self {
+        Self {
+            move_delay: Duration::from_millis(250),
+            end_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// First line of a cast file
+#[derive(Serialize)]
+struct Header {
+    version: u8,
+    width: u16,
+    height: u16,
+    title: String,
+    env: HeaderEnv,
+}
+
+#[derive(Serialize)]
+struct HeaderEnv {
+    #[serde(rename = "TERM")]
+    term: &'static str,
+}
+
+/// One line of output at `time` seconds into the recording
+fn write_event<W: Write>(out: &mut W, time: Duration, data: &[u8]) -> io::Result<()> {
+    let data = String::from_utf8_lossy(data);
+    serde_json::to_writer(&mut *out, &(time.as_secs_f64(), "o", data))?;
+    writeln!(out)
+}
+
+/// Write the replay as an asciinema v2 recording: one screen per position (`states`, as from
+/// `Replay::states`), `timing.move_delay` apart, in the current theme's colors
+pub fn write_cast<const N: usize, W: Write>(replay: &Replay, states: &[GameState<N>], timing: CastTiming, mut out: W) -> io::Result<()> {
+    let frames: Vec<_> = states.iter().map(game_frame).collect();
+
+    // room for the largest board, plus the line the cursor is left on
+    let header = Header {
+        version: 2,
+        width: frames.iter().map(|f| f.width()).max().unwrap_or(0),
+        height: frames.iter().map(|f| f.height()).max().unwrap_or(0) + 1,
+        title: format!("Replay of {}", replay.player),
+        env: HeaderEnv { term: "xterm-256color" },
+    };
+    serde_json::to_writer(&mut out, &header)?;
+    writeln!(out)?;
+
+    let mut time = Duration::ZERO;
+    for (i, frame) in frames.iter().enumerate() {
+        let mut data = Vec::new();
+        if i == 0 {
+            queue!(data, cursor::Hide)?;
+        }
+        queue_screen(&mut data, frame)?;
+
+        time = timing.move_delay * i as u32;
+        write_event(&mut out, time, &data)?;
+    }
+
+    let mut data = Vec::new();
+    queue!(data, cursor::Show)?;
+    write_event(&mut out, time + timing.end_delay, &data)?;
+
+    out.flush()
+}
@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::game_traits::FullGame;
+use crate::replay::tests::record_game;
+use crate::tui::CastTiming;
+use crate::tui::write_cast;
+
+#[test]
+fn test_write_cast() {
+    let (replay, states) = record_game(9, 4);
+
+    let timing = CastTiming {
+        move_delay: Duration::from_millis(300),
+        end_delay: Duration::from_secs(1),
+    };
+    let mut out = Vec::new();
+    write_cast(&replay, &states, timing, &mut out).unwrap();
+    let lines: Vec<Value> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+    let header = &lines[0];
+    assert_eq!(header["version"], 2);
+    assert_eq!(header["title"], "Replay of test");
+    // four 7-wide cells, and the board, a blank line, the score and the cursor's line
+    assert_eq!(header["width"], 28);
+    assert_eq!(header["height"], 7);
+
+    // a screen per position, then one last event holding the final position
+    let events = &lines[1..];
+    assert_eq!(events.len(), states.len() + 1);
+    for (i, state) in states.iter().enumerate() {
+        let time = events[i][0].as_f64().unwrap();
+        assert!((time - 0.3 * i as f64).abs() < 1e-9);
+        assert_eq!(events[i][1], "o");
+
+        let data = events[i][2].as_str().unwrap();
+        assert!(data.contains("\u{1b}[2J"), "every screen starts by clearing");
+        assert!(data.contains(&format!("Score: {}", state.current_score())));
+    }
+    let end = events.last().unwrap()[0].as_f64().unwrap();
+    assert!((end - (0.3 * (states.len() - 1) as f64 + 1.0)).abs() < 1e-9);
+}
@@ -0,0 +1,178 @@
+//! Several agents playing side by side on the same spawn sequence
+
+use std::io;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+
+use super::AlternateScreen;
+use super::DEFAULT_STEP_DELAY;
+use super::Frame;
+use super::MAX_STEP_DELAY;
+use super::MIN_STEP_DELAY;
+use super::Style;
+use super::board_width;
+use super::draw_board;
+use super::present;
+use crate::agents::Agent;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::game_structs::RngPlacement;
+use crate::game_structs::SpawnPolicy;
+use crate::game_traits::FullGame;
+
+/// Horizontal space between boards
+const BOARD_GAP: u16 = 4;
+
+/// One agent and its own copy of the game
+struct Contestant<const N: usize> {
+    agent: Box<dyn Agent<N>>,
+    name: String,
+    rng: RngPlacement,
+    game: GameState<N>,
+    num_moves: usize,
+    /// Set if the agent picked an illegal move; it would pick the same one forever, so we stop
+    illegal_move: Option<Move>,
+}
+
+impl<const N: usize> Contestant<N> {
+    fn new(agent: Box<dyn Agent<N>>, seed: u64, spawn_policy: SpawnPolicy) -> Self {
+        let mut rng = RngPlacement::new_from_seed(seed).with_spawn_policy(spawn_policy);
+        let game = GameState::new_random(&mut rng);
+        Self {
+            name: agent.name(),
+            agent,
+            rng,
+            game,
+            num_moves: 0,
+            illegal_move: None,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.illegal_move.is_some() || self.game.is_finished()
+    }
+
+    fn step(&mut self) -> io::Result<()> {
+        if self.is_done() {
+            return Ok(());
+        }
+
+        let next_move = self.agent.choose_move(&self.game)?;
+        match self.game.apply_move(next_move, &mut self.rng) {
+            Ok(new_state) => {
+                self.game = new_state;
+                self.num_moves += 1;
+            }
+            Err(_) => self.illegal_move = Some(next_move),
+        }
+        Ok(())
+    }
+
+    fn status(&self) -> String {
+        match self.illegal_move {
+            Some(m) => format!("stuck (illegal {m:?})"),
+            None if self.game.is_finished() => "game over".to_string(),
+            None => "playing".to_string(),
+        }
+    }
+}
+
+fn render_all<const N: usize>(contestants: &[Contestant<N>], seed: u64, paused: bool, step_delay: Duration) -> io::Result<()> {
+    let mut frame = Frame::new();
+
+    let state = if paused { "PAUSED" } else { "running" };
+    frame.push_line(format!(
+        "Comparing {} agents on seed {seed} | {state} | {}ms per move | [space] pause  [+/-] speed  [q] quit",
+        contestants.len(),
+        step_delay.as_millis()
+    ));
+
+    // every board gets the space of the widest one, so they don't shift around as tiles grow
+    let width = contestants.iter().map(|c| board_width(&c.game)).max().unwrap_or(0);
+    let top = 2;
+
+    for (i, c) in contestants.iter().enumerate() {
+        let col = i as u16 * (width + BOARD_GAP);
+        draw_board(&mut frame, &c.game, col, top);
+
+        let name: String = c.name.chars().take(width as usize).collect();
+        let lines = [
+            name,
+            format!("Score: {}", c.game.current_score()),
+            format!("Moves: {}", c.num_moves),
+            c.status(),
+        ];
+        for (j, line) in lines.into_iter().enumerate() {
+            frame.put_str(col, top + N as u16 + 1 + j as u16, &line, Style::default());
+        }
+    }
+
+    present(&frame)
+}
+
+/// Run every agent on its own game, all seeded identically, and draw the boards side by side.
+/// Stops when every game is finished (or on q).
+pub fn compare<const N: usize>(seed: Option<u64>, spawn_policy: SpawnPolicy, agents: Vec<Box<dyn Agent<N>>>) -> io::Result<()> {
+    let seed = seed.unwrap_or_else(rand::random);
+    let mut contestants: Vec<Contestant<N>> = agents.into_iter().map(|agent| Contestant::new(agent, seed, spawn_policy)).collect();
+
+    // prepare terminal
+    let mut screen = AlternateScreen::enter()?;
+
+    let mut paused = false;
+    let mut step_delay = DEFAULT_STEP_DELAY;
+    let mut last_step = Instant::now();
+
+    render_all(&contestants, seed, paused, step_delay)?;
+
+    while !contestants.iter().all(|c| c.is_done()) {
+        if !paused && last_step.elapsed() >= step_delay {
+            last_step = Instant::now();
+            for c in contestants.iter_mut() {
+                c.step()?;
+            }
+            render_all(&contestants, seed, paused, step_delay)?;
+            continue;
+        }
+
+        let timeout = if paused {
+            Duration::from_millis(100)
+        } else {
+            step_delay.saturating_sub(last_step.elapsed())
+        };
+
+        if crossterm::event::poll(timeout)?
+            && let Event::Key(key) = crossterm::event::read()?
+        {
+            match key.code {
+                KeyCode::Char('q') => break,
+                KeyCode::Char(' ') => paused = !paused,
+                KeyCode::Char('+') | KeyCode::Char('=') => step_delay = (step_delay / 2).max(MIN_STEP_DELAY),
+                KeyCode::Char('-') => step_delay = (step_delay * 2).min(MAX_STEP_DELAY),
+                _ => continue,
+            }
+            render_all(&contestants, seed, paused, step_delay)?;
+        }
+    }
+
+    // cleanup
+    screen.leave()?;
+    drop(screen);
+
+    println!("Results on seed {seed}:");
+    for c in &contestants {
+        println!(
+            "  {:<40} score {:>7} | highest tile {:>6} | {:>5} moves | {}",
+            c.name,
+            c.game.current_score(),
+            c.game.highest_tile(),
+            c.num_moves,
+            c.status()
+        );
+    }
+
+    Ok(())
+}
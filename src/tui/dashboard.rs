@@ -0,0 +1,201 @@
+//! Live terminal dashboard for a training run, as an alternative to printing lines per batch
+
+use std::io;
+use std::io::Write;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::cursor;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::execute;
+use crossterm::queue;
+use crossterm::terminal;
+
+use super::Frame;
+use super::draw_board;
+use super::present;
+use super::sparkline;
+use crate::game_structs::GameState;
+use crate::game_traits::FullGame;
+use crate::training::BatchStats;
+use crate::training::TrainingConfig;
+use crate::training::TrainingMode;
+use crate::training::TrainingObserver;
+
+/// Number of batches shown in each chart
+const CHART_WIDTH: usize = 60;
+
+/// Redrawing the board on every self-play move would slow training down a lot
+const BOARD_REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Row where the self-play board is drawn
+const BOARD_TOP: u16 = 13;
+
+/// Pulls one charted number out of a batch's stats
+type Metric = fn(&BatchStats) -> f32;
+
+/// Full-screen view of a training run: charts of each batch's stats, and the self-play game
+/// being played. Press q to stop training after the current game.
+pub struct TrainingDashboard {
+    config: TrainingConfig,
+    history: Vec<BatchStats>,
+    last_board_draw: Instant,
+    stop_requested: bool,
+}
+
+impl TrainingDashboard {
+    /// Takes over the terminal until the dashboard is dropped
+    pub fn start(config: &TrainingConfig) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let out = Self {
+            config: config.clone(),
+            history: Vec::new(),
+            last_board_draw: Instant::now(),
+            stop_requested: false,
+        };
+        out.render()?;
+        Ok(out)
+    }
+
+    /// Check (without blocking) whether q has been pressed
+    fn check_keys(&mut self) -> io::Result<()> {
+        while crossterm::event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = crossterm::event::read()?
+                && key.code == KeyCode::Char('q')
+                && !self.stop_requested
+            {
+                self.stop_requested = true;
+                self.render()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render(&self) -> io::Result<()> {
+        let config = &self.config;
+        let weights = &config.reward_weights;
+
+        let title = if self.stop_requested {
+            "ai2048 training | stopping after the current game..."
+        } else {
+            "ai2048 training | [q] stop early"
+        };
+
+        let mut lines = vec![
+            title.to_string(),
+            format!(
+                "lr {} | {} games/batch | {} learning steps/batch | discount {} | l2 {}",
+                config.learning_rate, config.games_per_batch, config.learning_steps_per_batch, config.discount_factor, config.l2_reg
+            ),
+            format!(
+                "reward weights: score {} | log merge {} | empty cells {} | survival {} | game over {} | max tile {}",
+                weights.score_delta, weights.log_merge, weights.empty_cells, weights.survival, weights.game_over, weights.max_tile
+            ),
+        ];
+        if let TrainingMode::AlphaZero(alphazero) = &config.mode {
+            let mcts = alphazero.mcts;
+            lines.push(format!(
+                "alphazero: {} sims/move | priors {} | {:?} leaves | first {} moves sampled | critic {}",
+                mcts.simulations,
+                mcts.priors,
+                mcts.leaf,
+                alphazero.sample_moves,
+                match alphazero.bootstrap_steps {
+                    Some(steps) => format!("bootstrapped after {steps} moves"),
+                    None => "toward the rest of the score".to_string(),
+                }
+            ));
+        }
+        lines.push(String::new());
+
+        match self.history.last() {
+            Some(last) => {
+                let eta = (config.max_time_sec as f64 - last.total_secs).max(0.0);
+                lines.push(format!(
+                    "batch {} | elapsed {:.0}s | ETA {:.0}s | {:.2} games/s | {:.0} steps/s",
+                    last.batch_idx,
+                    last.total_secs,
+                    eta,
+                    last.num_games as f64 / last.batch_secs,
+                    last.num_steps as f64 / last.batch_secs
+                ));
+            }
+            None => lines.push("waiting for the first batch...".to_string()),
+        }
+        lines.push(String::new());
+
+        let charts: [(&str, Metric); 5] = [
+            ("mean score", |s| s.mean_score),
+            ("max tile", |s| s.max_tile as f32),
+            ("actor loss", |s| s.actor_loss),
+            ("critic loss", |s| s.critic_loss),
+            ("entropy", |s| s.entropy),
+        ];
+        for (label, metric) in charts {
+            let values: Vec<f32> = self.history.iter().map(metric).collect();
+            let last = values.last().map(|v| format!("{v:.2}")).unwrap_or_default();
+            lines.push(format!(
+                "{label:<12} {:<width$} {last}",
+                sparkline(&values, CHART_WIDTH),
+                width = CHART_WIDTH
+            ));
+        }
+        lines.push(String::new());
+        lines.push("latest self-play game:".to_string());
+
+        let mut frame = Frame::new();
+        for line in lines {
+            frame.push_line(line);
+        }
+
+        present(&frame)
+    }
+
+    fn render_board<const N: usize>(&self, game: &GameState<N>) -> io::Result<()> {
+        let mut frame = Frame::new();
+        draw_board(&mut frame, game, 0, 0);
+        frame.push_line("");
+        frame.push_line(format!("score {} | highest tile {}", game.current_score(), game.highest_tile()));
+
+        // drawn over the top of the rest of the dashboard, so clear the old board first; a new
+        // game's board can be narrower than the last one
+        let mut stdout = io::stdout();
+        for row in BOARD_TOP..BOARD_TOP + frame.height() {
+            queue!(stdout, cursor::MoveTo(0, row), terminal::Clear(terminal::ClearType::CurrentLine))?;
+        }
+        frame.write_to(&mut stdout, 0, BOARD_TOP)?;
+        stdout.flush()
+    }
+}
+
+impl<const N: usize> TrainingObserver<N> for TrainingDashboard {
+    fn on_move(&mut self, state: &GameState<N>) -> io::Result<()> {
+        if self.last_board_draw.elapsed() >= BOARD_REDRAW_INTERVAL {
+            self.last_board_draw = Instant::now();
+            self.render_board(state)?;
+            self.check_keys()?;
+        }
+        Ok(())
+    }
+
+    fn on_batch(&mut self, stats: &BatchStats) -> io::Result<()> {
+        self.history.push(stats.clone());
+        self.render()
+    }
+
+    fn should_stop(&mut self) -> io::Result<bool> {
+        self.check_keys()?;
+        Ok(self.stop_requested)
+    }
+}
+
+impl Drop for TrainingDashboard {
+    fn drop(&mut self) {
+        // nothing sensible to do if this fails; we're on the way out anyway
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,161 @@
+//! In-memory grid of styled characters. Views draw into a `Frame`, which is then written to the
+//! terminal (or any other `Write`) in one go, or inspected as plain text in tests.
+
+use std::io;
+use std::io::Write;
+
+use crossterm::cursor;
+use crossterm::queue;
+use crossterm::style::Color;
+use crossterm::style::Print;
+use crossterm::style::ResetColor;
+use crossterm::style::SetBackgroundColor;
+use crossterm::style::SetForegroundColor;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+}
+
+impl Style {
+    pub fn fg(color: Color) -> Self {
+        Self { fg: Some(color), bg: None }
+    }
+
+    pub fn with_bg(self, color: Color) -> Self {
+        Self { bg: Some(color), ..self }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+/// Grows as things are drawn into it, so there's no need to know the size up front
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Frame {
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn height(&self) -> u16 {
+        self.rows.len() as u16
+    }
+
+    /// Width of the longest row
+    pub fn width(&self) -> u16 {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0) as u16
+    }
+
+    #[cfg(test)]
+    pub fn cell(&self, col: u16, row: u16) -> Option<Cell> {
+        self.rows.get(row as usize)?.get(col as usize).copied()
+    }
+
+    fn set(&mut self, col: u16, row: u16, cell: Cell) {
+        let (col, row) = (col as usize, row as usize);
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        let line = &mut self.rows[row];
+        if line.len() <= col {
+            line.resize(col + 1, Cell::default());
+        }
+        line[col] = cell;
+    }
+
+    /// Draw a single line of text starting at (col, row)
+    pub fn put_str(&mut self, col: u16, row: u16, text: &str, style: Style) {
+        for (i, ch) in text.chars().enumerate() {
+            self.set(col + i as u16, row, Cell { ch, style });
+        }
+    }
+
+    /// Add a line of unstyled text below everything drawn so far
+    pub fn push_line(&mut self, text: impl AsRef<str>) {
+        let row = self.height();
+        self.put_str(0, row, text.as_ref(), Style::default());
+        if text.as_ref().is_empty() {
+            self.rows.push(Vec::new());
+        }
+    }
+
+    /// Copy another frame into this one, with its top-left corner at (col, row)
+    pub fn blit(&mut self, other: &Frame, col: u16, row: u16) {
+        for (y, line) in other.rows.iter().enumerate() {
+            for (x, cell) in line.iter().enumerate() {
+                self.set(col + x as u16, row + y as u16, *cell);
+            }
+        }
+    }
+
+    /// The characters without any styling, one line per row, trailing spaces trimmed
+    #[cfg(test)]
+    pub fn to_text(&self) -> String {
+        self.rows
+            .iter()
+            .map(|line| line.iter().map(|cell| cell.ch).collect::<String>().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Queue the frame with its top-left corner at (col, row). Colors are only changed when the
+    /// style changes, and reset at the end of each row.
+    pub fn write_to<W: Write>(&self, out: &mut W, col: u16, row: u16) -> io::Result<()> {
+        for (y, line) in self.rows.iter().enumerate() {
+            queue!(out, cursor::MoveTo(col, row + y as u16))?;
+
+            let mut current = Style::default();
+            let mut run = String::new();
+
+            for cell in line {
+                if cell.style != current {
+                    flush_run(out, &mut run, current)?;
+                    current = cell.style;
+                }
+                run.push(cell.ch);
+            }
+            flush_run(out, &mut run, current)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Write out a run of same-styled characters
+fn flush_run<W: Write>(out: &mut W, run: &mut String, style: Style) -> io::Result<()> {
+    if run.is_empty() {
+        return Ok(());
+    }
+
+    if style == Style::default() {
+        queue!(out, Print(&run))?;
+    } else {
+        if let Some(bg) = style.bg {
+            queue!(out, SetBackgroundColor(bg))?;
+        }
+        if let Some(fg) = style.fg {
+            queue!(out, SetForegroundColor(fg))?;
+        }
+        queue!(out, Print(&run), ResetColor)?;
+    }
+
+    run.clear();
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! What the agent thinks while it plays: a heatmap of its move probabilities and a timeline
+//! of its value estimate, drawn next to the board during autoplay
+
+use super::Frame;
+use super::Style;
+use super::sparkline;
+use super::theme::Theme;
+use crate::agents::MoveEvaluation;
+use crate::game_structs::Move;
+
+/// Width of a probability bar
+const BAR_WIDTH: usize = 20;
+
+/// Number of moves shown in the value timeline
+const TIMELINE_WIDTH: usize = 40;
+
+/// Draw the overlay for the position `eval` describes, with its top-left corner at (col, row).
+/// `legal` says which moves (by index) are legal there, and `values` is the value estimate at
+/// every position so far, oldest first.
+pub fn draw_overlay(frame: &mut Frame, eval: &MoveEvaluation, legal: [bool; 4], values: &[f32], theme: Theme, col: u16, row: u16) {
+    let mut row = row;
+
+    match (&eval.policy, &eval.expected_scores) {
+        (Some(policy), _) => {
+            frame.put_str(col, row, "Policy", Style::default());
+            row += 1;
+            for m in Move::ALL {
+                let p = policy[m.to_idx()];
+                frame.blit(&bar_row(m, p, format!("{:>5.1}%", p * 100.0), legal[m.to_idx()], theme), col, row);
+                row += 1;
+            }
+        }
+        (None, Some(scores)) => {
+            // no probabilities, so shade each move by where its expected score falls between
+            // the worst and best legal moves
+            frame.put_str(col, row, "Expected score", Style::default());
+            row += 1;
+            let worst = scores.iter().flatten().copied().fold(f32::INFINITY, f32::min);
+            let best = scores.iter().flatten().copied().fold(f32::NEG_INFINITY, f32::max);
+            for m in Move::ALL {
+                let (fraction, label) = match scores[m.to_idx()] {
+                    Some(score) if best > worst => ((score - worst) / (best - worst), format!("{score:>9.1}")),
+                    Some(score) => (1.0, format!("{score:>9.1}")),
+                    None => (0.0, format!("{:>9}", "-")),
+                };
+                frame.blit(&bar_row(m, fraction, label, legal[m.to_idx()], theme), col, row);
+                row += 1;
+            }
+        }
+        (None, None) => {
+            frame.put_str(col, row, "(no move preferences from this agent)", Style::default());
+            row += 1;
+        }
+    }
+
+    if eval.value.is_none() && values.is_empty() {
+        return;
+    }
+
+    row += 1;
+    let current = eval.value.map(|v| format!("{v:.2}")).unwrap_or_else(|| "-".to_string());
+    frame.put_str(col, row, &format!("Critic value: {current}"), Style::default());
+    row += 1;
+
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let lo = finite.clone().fold(f32::INFINITY, f32::min);
+    let hi = finite.fold(f32::NEG_INFINITY, f32::max);
+    frame.put_str(col, row, &sparkline(values, TIMELINE_WIDTH), Style::default());
+    row += 1;
+    if lo <= hi {
+        frame.put_str(
+            col,
+            row,
+            &format!("last {} moves, range {lo:.2} to {hi:.2}", values.len().min(TIMELINE_WIDTH)),
+            Style::default(),
+        );
+    }
+}
+
+/// One line: the move, a bar shaded by `fraction` (0 to 1), and a label
+fn bar_row(m: Move, fraction: f32, label: String, legal: bool, theme: Theme) -> Frame {
+    let fraction = if fraction.is_finite() { fraction.clamp(0.0, 1.0) } else { 0.0 };
+    let filled = (fraction * BAR_WIDTH as f32).round() as usize;
+    let bar = "█".repeat(filled) + &"░".repeat(BAR_WIDTH - filled);
+    let suffix = if legal { "" } else { " (illegal)" };
+
+    let mut frame = Frame::new();
+    frame.put_str(0, 0, &format!("{:<6}", format!("{m:?}")), Style::default());
+    frame.put_str(6, 0, &bar, theme.heat_style(fraction));
+    frame.put_str(6 + BAR_WIDTH as u16, 0, &format!(" {label}{suffix}"), Style::default());
+    frame
+}
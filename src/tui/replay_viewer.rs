@@ -0,0 +1,178 @@
+//! Step back and forth through a recorded game
+
+use std::fs;
+use std::io;
+use std::time::Duration;
+use std::time::Instant;
+
+use crossterm::cursor;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::execute;
+use crossterm::terminal;
+
+use super::DEFAULT_STEP_DELAY;
+use super::Frame;
+use super::MAX_STEP_DELAY;
+use super::MIN_STEP_DELAY;
+use super::game_frame;
+use super::present;
+use crate::game_structs::GameState;
+use crate::game_traits::FullGame;
+use crate::notation::Position;
+use crate::replay::Replay;
+use crate::svg;
+
+/// Where we are in the replay and how we got here
+struct ViewerStatus {
+    /// Number of moves made so far; 0 is the starting position
+    position: usize,
+    playing: bool,
+    step_delay: Duration,
+    /// Digits typed so far for a "go to move" jump
+    goto_input: String,
+    /// Outcome of the last export, until the next keypress
+    message: Option<String>,
+}
+
+fn render_position<const N: usize>(replay: &Replay, states: &[GameState<N>], status: &ViewerStatus) -> io::Result<()> {
+    let mut frame = Frame::new();
+
+    let position = status.position;
+    let game = &states[position];
+    let seed = replay.seed.map(|s| s.to_string()).unwrap_or_else(|| "unknown".to_string());
+
+    frame.push_line(format!(
+        "Replay of {} | seed {seed} | move {position}/{}",
+        replay.player,
+        replay.num_moves()
+    ));
+    frame.push_line("");
+    frame.blit(&game_frame(game), 0, 2);
+
+    let mut lines = vec![String::new()];
+
+    match position.checked_sub(1).map(|i| &replay.steps[i]) {
+        Some(step) => lines.push(format!(
+            "Last move: {:?} | spawned {} at (x={}, y={}) | score +{}",
+            step.m, step.spawn.value, step.spawn.x, step.spawn.y, step.score_delta
+        )),
+        None => lines.push("Starting position".to_string()),
+    }
+    match replay.steps.get(position) {
+        Some(step) => lines.push(format!("Next move: {:?}", step.m)),
+        None if game.is_finished() => lines.push("Game over".to_string()),
+        None => lines.push("End of recording".to_string()),
+    }
+
+    // so an interesting position can be practiced with play --from
+    lines.push(format!("Position: {}", Position::from_state(game)));
+
+    lines.push(String::new());
+    let state = if status.playing { "playing" } else { "paused" };
+    lines.push(format!("{state} | {}ms per move", status.step_delay.as_millis()));
+    if !status.goto_input.is_empty() {
+        lines.push(format!("Go to move: {}_ (enter to jump, esc to cancel)", status.goto_input));
+    }
+    if let Some(message) = &status.message {
+        lines.push(message.clone());
+    }
+    lines.push(
+        "[left/right] step  [home/end] start/end  [digits + enter] go to move  [space] play/pause  [+/-] speed  [e] export SVG  [q] quit"
+            .into(),
+    );
+
+    for line in lines {
+        frame.push_line(line);
+    }
+
+    present(&frame)
+}
+
+/// Save the position as an SVG in the working directory, with an arrow for the move made from it
+fn export_position<const N: usize>(replay: &Replay, states: &[GameState<N>], position: usize) -> String {
+    let path = format!("replay-move-{position}.svg");
+    let next = replay.steps.get(position).map(|step| step.m);
+    match fs::write(&path, svg::render_board(&states[position], next)) {
+        Ok(()) => format!("Saved {path}"),
+        Err(e) => format!("Couldn't save {path}: {e}"),
+    }
+}
+
+/// Interactive viewer for a recorded game
+pub fn view_replay<const N: usize>(replay: &Replay) -> io::Result<()> {
+    let states = replay.states::<N>()?;
+    let last = states.len() - 1;
+
+    // prepare terminal
+    terminal::enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let mut status = ViewerStatus {
+        position: 0,
+        playing: false,
+        step_delay: DEFAULT_STEP_DELAY,
+        goto_input: String::new(),
+        message: None,
+    };
+    let mut last_step = Instant::now();
+
+    render_position(replay, &states, &status)?;
+
+    loop {
+        if status.playing && last_step.elapsed() >= status.step_delay {
+            last_step = Instant::now();
+            status.position = (status.position + 1).min(last);
+            status.playing = status.position < last;
+            render_position(replay, &states, &status)?;
+            continue;
+        }
+
+        let timeout = if status.playing {
+            status.step_delay.saturating_sub(last_step.elapsed())
+        } else {
+            Duration::from_millis(100)
+        };
+
+        if crossterm::event::poll(timeout)?
+            && let Event::Key(key) = crossterm::event::read()?
+        {
+            status.message = None;
+            match key.code {
+                KeyCode::Char('q') => break,
+                KeyCode::Char('e') => status.message = Some(export_position(replay, &states, status.position)),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => status.position = (status.position + 1).min(last),
+                KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => status.position = status.position.saturating_sub(1),
+                KeyCode::Home | KeyCode::Char('g') => status.position = 0,
+                KeyCode::End | KeyCode::Char('G') => status.position = last,
+                KeyCode::Char(' ') => {
+                    status.playing = !status.playing && status.position < last;
+                    last_step = Instant::now();
+                }
+                KeyCode::Char('+') | KeyCode::Char('=') => status.step_delay = (status.step_delay / 2).max(MIN_STEP_DELAY),
+                KeyCode::Char('-') => status.step_delay = (status.step_delay * 2).min(MAX_STEP_DELAY),
+                KeyCode::Char(c) if c.is_ascii_digit() => status.goto_input.push(c),
+                KeyCode::Backspace => {
+                    status.goto_input.pop();
+                }
+                KeyCode::Esc => status.goto_input.clear(),
+                KeyCode::Enter => {
+                    if let Ok(target) = status.goto_input.parse::<usize>() {
+                        status.position = target.min(last);
+                    }
+                    status.goto_input.clear();
+                }
+                _ => continue,
+            }
+
+            render_position(replay, &states, &status)?;
+        }
+    }
+
+    // cleanup
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::style::Color;
+
+use crate::agents::Agent;
+use crate::agents::MoveEvaluation;
+use crate::game_structs::GameState;
+use crate::game_structs::Move;
+use crate::tui::AutoplayStatus;
+use crate::tui::Frame;
+use crate::tui::Style;
+use crate::tui::Theme;
+use crate::tui::board_width;
+use crate::tui::cell_width;
+use crate::tui::draw_board;
+use crate::tui::draw_board_themed;
+use crate::tui::draw_overlay;
+use crate::tui::format_elapsed;
+use crate::tui::game_frame;
+use crate::tui::sparkline;
+
+fn sample_game() -> GameState<4> {
+    #[rustfmt::skip]
+    let out = GameState::from_grid(
+        [
+            [0, 1, 2, 3],
+            [4, 5, 6, 7],
+            [8, 9, 10, 11],
+            [0, 0, 0, 17],
+        ],
+        1234,
+    );
+    out
+}
+
+#[test]
+fn test_game_frame_snapshot() {
+    let frame = game_frame(&sample_game());
+
+    // cells are wide enough for the six digits of 131072, plus a space and the borders
+    let expected = [
+        "|      .||      2||      4||      8|",
+        "|     16||     32||     64||    128|",
+        "|    256||    512||   1024||   2048|",
+        "|      .||      .||      .|| 131072|",
+        "",
+        "Score: 1234",
+    ]
+    .join("\n");
+
+    assert_eq!(frame.to_text(), expected);
+}
+
+#[test]
+fn test_cell_width_follows_largest_tile() {
+    assert_eq!(cell_width(0), 7);
+    assert_eq!(cell_width(2), 7);
+    assert_eq!(cell_width(8192), 7);
+    assert_eq!(cell_width(16384), 8);
+    assert_eq!(cell_width(1 << 20), 10);
+
+    let small = GameState::<4>::from_grid([[1, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 11]], 0);
+    assert_eq!(board_width(&small), 28);
+    assert_eq!(board_width(&sample_game()), 36);
+
+    // a million doesn't run into the border
+    let mut frame = Frame::new();
+    draw_board(&mut frame, &GameState::<2>::from_grid([[20, 0], [0, 1]], 0), 0, 0);
+    assert_eq!(frame.to_text(), "| 1048576||       .|\n|       .||       2|");
+}
+
+#[test]
+fn test_board_colors() {
+    let mut frame = Frame::new();
+    draw_board_themed(&mut frame, &sample_game(), Theme::Ansi, 0, 0);
+
+    let style_at = |x: u16, y: u16| frame.cell(x * 9 + 3, y).unwrap().style;
+
+    assert_eq!(style_at(0, 0), Style::fg(Color::DarkGrey).with_bg(Color::Black), "empty");
+    assert_eq!(style_at(1, 0), Style::fg(Color::Grey).with_bg(Color::Black), "2");
+    assert_eq!(style_at(1, 1), Style::fg(Color::Magenta).with_bg(Color::Black), "32");
+    assert_eq!(style_at(1, 2), Style::fg(Color::Green).with_bg(Color::Black), "512");
+    assert_eq!(style_at(3, 3), Style::fg(Color::White).with_bg(Color::DarkMagenta), "131072");
+
+    let mut frame = Frame::new();
+    draw_board_themed(&mut frame, &sample_game(), Theme::Mono, 0, 0);
+    assert_eq!(frame.cell(3, 3).unwrap().style, Style::default());
+}
+
+#[test]
+fn test_draw_board_at_offset() {
+    let mut frame = Frame::new();
+    frame.push_line("header");
+    draw_board(&mut frame, &GameState::<2>::from_grid([[1, 0], [0, 2]], 0), 3, 2);
+
+    let expected = ["header", "", "   |    2||    .|", "   |    .||    4|"].join("\n");
+    assert_eq!(frame.to_text(), expected);
+}
+
+#[test]
+fn test_blit_and_write_to() {
+    let mut inner = Frame::new();
+    inner.put_str(0, 0, "ab", Style::fg(Color::Red));
+
+    let mut outer = Frame::new();
+    outer.push_line("xyz");
+    outer.blit(&inner, 1, 0);
+    assert_eq!(outer.to_text(), "xab");
+    assert_eq!(outer.cell(0, 0).unwrap().style, Style::default());
+    assert_eq!(outer.cell(2, 0).unwrap().style, Style::fg(Color::Red));
+
+    let mut out: Vec<u8> = Vec::new();
+    outer.write_to(&mut out, 0, 5).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    // moves to the requested row (1-indexed in ANSI), and colors only the styled run
+    assert!(out.starts_with("\x1b[6;1Hx"), "{out:?}");
+    assert!(out.contains("ab\x1b[0m"), "{out:?}");
+}
+
+#[test]
+fn test_sparkline() {
+    assert_eq!(sparkline(&[0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0], 8), "▁▂▃▄▅▆▇█");
+    assert_eq!(sparkline(&[0.0, 7.0, 14.0], 2), "▁█", "only the last values fit");
+    assert_eq!(sparkline(&[3.0, 3.0], 10), "▁▁", "flat lines sit at the bottom");
+    assert_eq!(sparkline(&[], 10), "");
+}
+
+#[test]
+fn test_format_elapsed() {
+    assert_eq!(format_elapsed(Duration::from_secs(0)), "0:00");
+    assert_eq!(format_elapsed(Duration::from_millis(75_900)), "1:15");
+    assert_eq!(format_elapsed(Duration::from_secs(3 * 3600 + 5 * 60 + 9)), "3:05:09");
+}
+
+#[test]
+fn test_policy_overlay() {
+    let eval = MoveEvaluation {
+        policy: Some([0.5, 0.25, 0.25, 0.0]),
+        value: Some(3.0),
+        expected_scores: None,
+    };
+
+    let mut frame = Frame::new();
+    draw_overlay(&mut frame, &eval, [true, true, true, false], &[1.0, 2.0, 3.0], Theme::Ansi, 2, 0);
+
+    let expected = [
+        "  Policy",
+        "  Up    ██████████░░░░░░░░░░  50.0%",
+        "  Down  █████░░░░░░░░░░░░░░░  25.0%",
+        "  Left  █████░░░░░░░░░░░░░░░  25.0%",
+        "  Right ░░░░░░░░░░░░░░░░░░░░   0.0% (illegal)",
+        "",
+        "  Critic value: 3.00",
+        "  ▁▅█",
+        "  last 3 moves, range 1.00 to 3.00",
+    ]
+    .join("\n");
+    assert_eq!(frame.to_text(), expected);
+
+    // the bars are shaded by probability
+    assert_eq!(frame.cell(8, 1).unwrap().style, Theme::Ansi.heat_style(0.5));
+    assert_ne!(frame.cell(8, 1).unwrap().style, frame.cell(8, 2).unwrap().style);
+}
+
+#[test]
+fn test_expected_score_overlay() {
+    let eval = MoveEvaluation {
+        policy: None,
+        value: None,
+        expected_scores: Some([Some(100.0), None, Some(50.0), Some(75.0)]),
+    };
+
+    let mut frame = Frame::new();
+    draw_overlay(&mut frame, &eval, [true, false, true, true], &[], Theme::Ansi, 0, 0);
+
+    let expected = [
+        "Expected score",
+        "Up    ████████████████████     100.0",
+        "Down  ░░░░░░░░░░░░░░░░░░░░         - (illegal)",
+        "Left  ░░░░░░░░░░░░░░░░░░░░      50.0",
+        "Right ██████████░░░░░░░░░░      75.0",
+    ]
+    .join("\n");
+    assert_eq!(frame.to_text(), expected);
+}
+
+/// Counts how often it's asked about a position, and always thinks it's worth 1
+#[derive(Default)]
+struct CountingAgent {
+    searches: usize,
+}
+
+impl Agent<4> for CountingAgent {
+    fn name(&self) -> String {
+        "counting".to_string()
+    }
+
+    fn choose_move(&mut self, _state: &GameState<4>) -> io::Result<Move> {
+        Ok(Move::Left)
+    }
+
+    fn evaluate(&mut self, _state: &GameState<4>) -> MoveEvaluation {
+        self.searches += 1;
+        MoveEvaluation {
+            value: Some(1.0),
+            ..Default::default()
+        }
+    }
+}
+
+#[test]
+fn test_autoplay_overlay_evaluates_each_position_once() {
+    let mut status = AutoplayStatus {
+        paused: false,
+        step_delay: Duration::from_millis(100),
+        wrong_moves: vec![],
+        show_overlay: true,
+        evaluated: None,
+        evaluation: None,
+        planned_move: None,
+        values: vec![],
+    };
+    let mut agent = CountingAgent::default();
+    let game = sample_game();
+
+    status.evaluate(&game, &mut agent).unwrap();
+    // toggling the overlay off and on again doesn't change the position
+    for _ in 0..2 {
+        status.show_overlay = !status.show_overlay;
+        status.evaluate(&game, &mut agent).unwrap();
+    }
+    assert_eq!(agent.searches, 1);
+    assert_eq!(status.values, vec![1.0]);
+    assert_eq!(status.planned_move, Some(Move::Left));
+    assert!(status.overlay(&game).is_some());
+
+    // a finished game isn't searched at all
+    let finished = GameState::<4>::from_grid([[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]], 0);
+    status.evaluate(&finished, &mut agent).unwrap();
+    assert_eq!(agent.searches, 1);
+    assert_eq!(status.planned_move, None);
+    assert!(status.overlay(&finished).is_none());
+}
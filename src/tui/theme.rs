@@ -0,0 +1,175 @@
+//! Tile colors. The terminal's 16 named colors work everywhere; true color is used when the
+//! terminal says it supports it, and there are monochrome and high-contrast options for
+//! terminals (or eyes) where colors don't help.
+
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crossterm::style::Color;
+
+use super::frame::Style;
+use crate::palette;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Theme {
+    /// The 16 named terminal colors
+    Ansi,
+    /// 24-bit colors, close to the original web game
+    TrueColor,
+    /// No colors at all, just the terminal's defaults
+    Mono,
+    /// Black on white tiles, white on black empty cells
+    HighContrast,
+}
+
+/// Theme choice from the command line; `Auto` picks one based on the environment
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ThemeChoice {
+    /// Whatever the terminal looks like it supports
+    #[default]
+    Auto,
+    /// This theme, whatever the terminal
+    Fixed(Theme),
+}
+
+impl ThemeChoice {
+    /// The theme to actually use
+    pub fn resolve(self) -> Theme {
+        match self {
+            ThemeChoice::Fixed(theme) => theme,
+            ThemeChoice::Auto => Theme::detect(),
+        }
+    }
+}
+
+impl FromStr for ThemeChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ThemeChoice::Auto),
+            "ansi" => Ok(ThemeChoice::Fixed(Theme::Ansi)),
+            "truecolor" => Ok(ThemeChoice::Fixed(Theme::TrueColor)),
+            "mono" => Ok(ThemeChoice::Fixed(Theme::Mono)),
+            "high-contrast" => Ok(ThemeChoice::Fixed(Theme::HighContrast)),
+            other => Err(format!(
+                "Unknown theme '{other}'; expected auto, ansi, truecolor, mono, or high-contrast"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ThemeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ThemeChoice::Auto => "auto",
+            ThemeChoice::Fixed(Theme::Ansi) => "ansi",
+            ThemeChoice::Fixed(Theme::TrueColor) => "truecolor",
+            ThemeChoice::Fixed(Theme::Mono) => "mono",
+            ThemeChoice::Fixed(Theme::HighContrast) => "high-contrast",
+        };
+        write!(f, "{name}")
+    }
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Set the theme used by every view. Only the first call has any effect.
+pub fn set_theme(choice: ThemeChoice) {
+    let _ = THEME.set(choice.resolve());
+}
+
+/// The theme set with `set_theme`, or the detected one if it was never set
+pub fn current_theme() -> Theme {
+    *THEME.get_or_init(Theme::detect)
+}
+
+/// Ends of the true-color heatmap scale
+const HEAT_COLD: (u8, u8, u8) = (70, 80, 170);
+const HEAT_HOT: (u8, u8, u8) = (246, 94, 59);
+
+fn rgb((r, g, b): (u8, u8, u8)) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+impl Theme {
+    /// True color if COLORTERM advertises it, nothing at all if NO_COLOR is set, otherwise the
+    /// named colors
+    pub fn detect() -> Self {
+        if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return Theme::Mono;
+        }
+        match env::var("COLORTERM") {
+            Ok(v) if v == "truecolor" || v == "24bit" => Theme::TrueColor,
+            _ => Theme::Ansi,
+        }
+    }
+
+    /// Style for a cell holding the given exponent (0 for empty)
+    pub fn tile_style(self, exponent: u8) -> Style {
+        match self {
+            Theme::Ansi => ansi_style(exponent),
+            Theme::TrueColor => Style::fg(rgb(palette::tile_text(exponent))).with_bg(rgb(palette::tile_background(exponent))),
+            Theme::Mono => Style::default(),
+            Theme::HighContrast => match exponent {
+                0 => Style::fg(Color::White).with_bg(Color::Black),
+                _ => Style::fg(Color::Black).with_bg(Color::White),
+            },
+        }
+    }
+
+    /// Style for a heatmap cell, where `fraction` runs from 0 (cold) to 1 (hot)
+    pub fn heat_style(self, fraction: f32) -> Style {
+        let fraction = fraction.clamp(0.0, 1.0);
+        match self {
+            Theme::Ansi => Style::fg(match fraction {
+                f if f < 0.1 => Color::DarkGrey,
+                f if f < 0.25 => Color::Blue,
+                f if f < 0.5 => Color::Cyan,
+                f if f < 0.75 => Color::Yellow,
+                _ => Color::Red,
+            }),
+            Theme::TrueColor => {
+                let lerp = |cold: u8, hot: u8| (cold as f32 + (hot as f32 - cold as f32) * fraction).round() as u8;
+                Style::fg(rgb((
+                    lerp(HEAT_COLD.0, HEAT_HOT.0),
+                    lerp(HEAT_COLD.1, HEAT_HOT.1),
+                    lerp(HEAT_COLD.2, HEAT_HOT.2),
+                )))
+            }
+            Theme::Mono | Theme::HighContrast => Style::default(),
+        }
+    }
+}
+
+/// Up to 512 these are the original colored digits on black; bigger tiles get a colored
+/// background so every tile through 2^17 still looks different
+fn ansi_style(exponent: u8) -> Style {
+    let on_black = |fg| Style::fg(fg).with_bg(Color::Black);
+    match exponent {
+        0 => on_black(Color::DarkGrey),
+        1 => on_black(Color::Grey),
+        2 => on_black(Color::White),
+        3 => on_black(Color::Yellow),
+        4 => on_black(Color::DarkYellow),
+        5 => on_black(Color::Magenta),
+        6 => on_black(Color::Red),
+        7 => on_black(Color::Blue),
+        8 => on_black(Color::Cyan),
+        9 => on_black(Color::Green),
+        10 => Style::fg(Color::Black).with_bg(Color::Green),
+        11 => Style::fg(Color::Black).with_bg(Color::Yellow),
+        12 => Style::fg(Color::White).with_bg(Color::Red),
+        13 => Style::fg(Color::White).with_bg(Color::Magenta),
+        14 => Style::fg(Color::White).with_bg(Color::Blue),
+        15 => Style::fg(Color::Black).with_bg(Color::Cyan),
+        16 => Style::fg(Color::White).with_bg(Color::DarkRed),
+        17 => Style::fg(Color::White).with_bg(Color::DarkMagenta),
+        _ => Style::fg(Color::Black).with_bg(Color::White),
+    }
+}
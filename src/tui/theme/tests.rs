@@ -0,0 +1,41 @@
+use std::collections::HashSet;
+
+use crate::tui::theme::Theme;
+use crate::tui::theme::ThemeChoice;
+
+#[test]
+fn test_colored_themes_distinguish_every_tile() {
+    for theme in [Theme::Ansi, Theme::TrueColor] {
+        let styles: HashSet<_> = (0..=17).map(|exp| theme.tile_style(exp)).collect();
+        assert_eq!(
+            styles.len(),
+            18,
+            "{theme:?} should have a distinct style for empty and 2 through 2^17"
+        );
+    }
+}
+
+#[test]
+fn test_high_contrast_separates_empty_from_tiles() {
+    let theme = Theme::HighContrast;
+    assert_ne!(theme.tile_style(0), theme.tile_style(1));
+    assert_eq!(theme.tile_style(1), theme.tile_style(17));
+}
+
+#[test]
+fn test_theme_choice_parsing() {
+    for name in ["auto", "ansi", "truecolor", "mono", "high-contrast"] {
+        let choice: ThemeChoice = name.parse().unwrap();
+        assert_eq!(choice.to_string(), name);
+    }
+    assert_eq!("mono".parse(), Ok(ThemeChoice::Fixed(Theme::Mono)));
+    assert!("rainbow".parse::<ThemeChoice>().is_err());
+}
+
+#[test]
+fn test_heat_style_runs_cold_to_hot() {
+    assert_ne!(Theme::Ansi.heat_style(0.0), Theme::Ansi.heat_style(1.0));
+    assert_ne!(Theme::TrueColor.heat_style(0.0), Theme::TrueColor.heat_style(1.0));
+    assert_eq!(Theme::TrueColor.heat_style(2.0), Theme::TrueColor.heat_style(1.0), "clamped");
+    assert_eq!(Theme::Mono.heat_style(1.0), Theme::Mono.heat_style(0.0));
+}
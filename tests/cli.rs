@@ -0,0 +1,20 @@
+//! Runs the `ai2048` binary itself, for behavior that lives in main
+
+#![cfg(feature = "cli")]
+
+use std::process::Command;
+
+use serde_json::Value;
+
+#[test]
+fn test_analyze_json_is_the_whole_output() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ai2048"))
+        .args(["analyze", "--board", "2,.,4,./.,.,.,./.,.,8,./.,.,.,2", "--rollouts", "5", "--json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let analysis: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(analysis["score"], 20);
+    assert_eq!(analysis["moves"].as_array().unwrap().len(), 4);
+}